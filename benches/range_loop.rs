@@ -0,0 +1,41 @@
+//! Times the range loop from `examples/main.alice`, scaled up, on both
+//! engines. Run with `cargo bench --bench range_loop`.
+
+use std::time::{Duration, Instant};
+
+use alice::{Engine, Interpreter, Program, Vm, scanner::Scanner};
+
+const SOURCE: &str = "
+let num = 0;
+for index in [0..200000] {
+  if index % 2 == 0 {
+    num = num + index;
+  }
+}
+";
+
+const RUNS: u32 = 10;
+
+fn main() {
+    let mut tree = Duration::ZERO;
+    let mut vm = Duration::ZERO;
+
+    for _ in 0..RUNS {
+        let program = parse(SOURCE);
+        let start = Instant::now();
+        Interpreter::new().run(program).unwrap();
+        tree += start.elapsed();
+
+        let program = parse(SOURCE);
+        let start = Instant::now();
+        Vm::new().run(program).unwrap();
+        vm += start.elapsed();
+    }
+
+    println!("range loop (tree): {:>8.2?} / run", tree / RUNS);
+    println!("range loop (vm):   {:>8.2?} / run", vm / RUNS);
+}
+
+fn parse(source: &str) -> Program {
+    Program::parse(source, Scanner::new(source).scan_tokens().unwrap()).unwrap()
+}
@@ -0,0 +1,94 @@
+//! Micro-benchmarks for the runtime value representation: arithmetic on
+//! immediates, array-heavy code, short strings, and copying values into
+//! variables and arguments, on both engines. Run with
+//! `cargo bench --bench values`.
+//!
+//! Milliseconds per run, best of four, with 32-byte values (aa7c959) and
+//! with the 16-byte values of 76caf79, which keep short strings inline and
+//! ranges behind an `Rc`:
+//!
+//! | workload   | tree before | tree after | vm before | vm after |
+//! |------------|-------------|------------|-----------|----------|
+//! | arithmetic |        93.0 |       86.7 |      82.3 |     79.7 |
+//! | arrays     |       179.9 |      180.4 |     144.0 |    141.9 |
+//! | strings    |        63.4 |       46.3 |      52.4 |     39.7 |
+//! | copies     |       122.7 |      108.2 |      89.1 |     74.2 |
+//!
+//! Arrays were already behind an `Rc`, so array-heavy code gains nothing.
+
+use std::time::{Duration, Instant};
+
+use alice::{Engine, Interpreter, Program, Vm, scanner::Scanner};
+
+const ARITHMETIC: &str = "
+let x = 0;
+let f = 0.5;
+for i in [0..200000] {
+  x = x + i * 3 - i / 2;
+  f = f * 0.5 + 0.25;
+}
+";
+
+const ARRAYS: &str = "
+let total = 0;
+for i in [0..100000] {
+  let a = [i, i + 1, i + 2, [i, i]];
+  a[1] = a[3];
+  total = total + a[0] + a[2] + a[1][0];
+  for x in a[3] {
+    total = total - x;
+  }
+}
+";
+
+const STRINGS: &str = r#"
+let last = "";
+for i in [0..100000] {
+  let key = "key:" + i;
+  if key == last {
+    println(key);
+  }
+  last = key;
+}
+"#;
+
+const COPIES: &str = "
+fn pick(a, b, c) { return b; }
+let word = \"inline\";
+let pair = [1, 2];
+let span = [0..3];
+let kept = nil;
+for i in [0..100000] {
+  kept = pick(word, pair, span);
+  kept = pick(span, word, 2.5);
+  let copy = word;
+}
+";
+
+const RUNS: u32 = 10;
+
+fn main() {
+    for (name, source) in [("arithmetic", ARITHMETIC), ("arrays", ARRAYS), ("strings", STRINGS), ("copies", COPIES)] {
+        let mut tree = Duration::ZERO;
+        let mut vm = Duration::ZERO;
+
+        for _ in 0..RUNS {
+            let program = parse(source);
+            let start = Instant::now();
+            Interpreter::new().run(program).unwrap();
+            tree += start.elapsed();
+
+            let program = parse(source);
+            let start = Instant::now();
+            Vm::new().run(program).unwrap();
+            vm += start.elapsed();
+        }
+
+        println!("{:<10} (tree): {:>8.2?} / run", name, tree / RUNS);
+        println!("{:<10} (vm):   {:>8.2?} / run", name, vm / RUNS);
+    }
+}
+
+fn parse(source: &str) -> Program {
+    Program::parse(source, Scanner::new(source).scan_tokens().unwrap()).unwrap()
+}
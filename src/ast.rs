@@ -1,30 +1,60 @@
-use crate::{token::Token, error::AliceError};
+use std::{rc::Rc, cell::RefCell, fmt::Debug, ops::Deref};
+
+use crate::{token::Token, error::AliceError, function::AliceFunction, native::{NativeFunction, NativeModule}, string::AliceString};
 
 pub trait VisitExpr<R> {
-    fn visit_grouping_expr(&mut self, expression: Expr) -> Result<R, AliceError>;
-    fn visit_variable_expr(&mut self, name: Token) -> Result<R, AliceError>;
-    fn visit_assign_expr(&mut self, name: Token, value: Expr) -> Result<R, AliceError>;
-    fn visit_unary_expr(&mut self, operator: Token, value: Expr) -> Result<R, AliceError>;
-    fn visit_binary_expr(&mut self, left: Expr, operator: Token, right: Expr) -> Result<R, AliceError>;
-    fn visit_logical_expr(&mut self, left: Expr, operator: Token, right: Expr) -> Result<R, AliceError>;
-    fn visit_call_expr(&mut self, callee: Expr, paren: Token, arguments: Vec<Expr>) -> Result<R, AliceError>;
-    fn visit_literal_expr(&mut self, value: AliceObject) -> Result<R, AliceError>;
-    fn visit_array_expr(&mut self, list: Vec<Expr>) -> Result<R, AliceError>;
-    fn visit_range_expr(&mut self, start: Expr, end: Expr, line: u32) -> Result<R, AliceError>;
-
-    fn evaluate(&mut self, expr: Expr) -> Result<R, AliceError> {
+    fn visit_grouping_expr(&mut self, expression: &Expr) -> Result<R, AliceError>;
+    fn visit_variable_expr(&mut self, name: &Token, binding: Option<Binding>) -> Result<R, AliceError>;
+    fn visit_assign_expr(&mut self, name: &Token, value: &Expr, binding: Option<Binding>) -> Result<R, AliceError>;
+    fn visit_unary_expr(&mut self, operator: &Token, value: &Expr) -> Result<R, AliceError>;
+    /// Applies `operator` to `left`, the chain evaluated so far, and `right`.
+    fn visit_binary_expr(&mut self, left: R, operator: &Token, right: &Expr) -> Result<R, AliceError>;
+    fn visit_logical_expr(&mut self, left: R, operator: &Token, right: &Expr) -> Result<R, AliceError>;
+    fn visit_call_expr(&mut self, callee: &Expr, site: &Rc<CallSite>, arguments: &[Expr]) -> Result<R, AliceError>;
+    fn visit_literal_expr(&mut self, value: &AliceObject) -> Result<R, AliceError>;
+    fn visit_array_expr(&mut self, list: &[Expr]) -> Result<R, AliceError>;
+    fn visit_index_expr(&mut self, object: &Expr, bracket: &Token, index: &Expr) -> Result<R, AliceError>;
+    fn visit_set_index_expr(&mut self, object: &Expr, bracket: &Token, index: &Expr, value: &Expr) -> Result<R, AliceError>;
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> Result<R, AliceError>;
+    fn visit_range_expr(&mut self, start: &Expr, dots: &Token, end: &Expr) -> Result<R, AliceError>;
+
+    fn evaluate(&mut self, expr: &Expr) -> Result<R, AliceError> {
         match expr {
-            Expr::Grouping { expression } => self.visit_grouping_expr(*expression),
-            Expr::Variable { name } => self.visit_variable_expr(name),
-            Expr::Assign { name, value } => self.visit_assign_expr(name, *value),
-            Expr::Unary { operator, value } => self.visit_unary_expr(operator, *value),
-            Expr::Binary { left, operator, right } => self.visit_binary_expr(*left, operator, *right),
-            Expr::Logical { left, operator, right } => self.visit_logical_expr(*left, operator, *right),
-            Expr::Call { callee, paren, arguments } => self.visit_call_expr(*callee, paren, arguments),
+            Expr::Grouping { expression } => self.visit_grouping_expr(expression),
+            Expr::Variable { name, binding } => self.visit_variable_expr(name, *binding),
+            Expr::Assign { name, value, binding } => self.visit_assign_expr(name, value, *binding),
+            Expr::Unary { operator, value } => self.visit_unary_expr(operator, value),
+            Expr::Binary { .. } | Expr::Logical { .. } if expr.is_long_chain() => self.evaluate_chain(expr),
+            Expr::Binary { left, operator, right } => {
+                let left = self.evaluate(left)?;
+                self.visit_binary_expr(left, operator, right)
+            }
+            Expr::Logical { left, operator, right } => {
+                let left = self.evaluate(left)?;
+                self.visit_logical_expr(left, operator, right)
+            }
+            Expr::Call { callee, site, arguments } => self.visit_call_expr(callee, site, arguments),
             Expr::Literal { value } => self.visit_literal_expr(value),
             Expr::Array { value } => self.visit_array_expr(value),
-            Expr::Range { start, end, line } => self.visit_range_expr(*start, *end, line)
+            Expr::Index { object, bracket, index } => self.visit_index_expr(object, bracket, index),
+            Expr::SetIndex { object, bracket, index, value } => self.visit_set_index_expr(object, bracket, index, value),
+            Expr::Get { object, name } => self.visit_get_expr(object, name),
+            Expr::Range { start, dots, end } => self.visit_range_expr(start, dots, end)
+        }
+    }
+
+    /// Evaluates a long chain of operators link by link, rather than
+    /// recursing down its left side.
+    fn evaluate_chain(&mut self, chain: &Expr) -> Result<R, AliceError> {
+        let (first, links) = chain.chain();
+        let mut value = self.evaluate(first)?;
+        for Link { operator, right, logical } in links {
+            value = match logical {
+                false => self.visit_binary_expr(value, operator, right)?,
+                true => self.visit_logical_expr(value, operator, right)?
+            };
         }
+        Ok(value)
     }
 }
 
@@ -34,11 +64,13 @@ pub enum Expr {
         expression: Box<Expr>
     },
     Variable {
-        name: Token
+        name: Token,
+        binding: Option<Binding>
     },
     Assign {
         name: Token,
-        value: Box<Expr>
+        value: Box<Expr>,
+        binding: Option<Binding>
     },
     Unary {
         operator: Token,
@@ -54,9 +86,11 @@ pub enum Expr {
         operator: Token,
         right: Box<Expr>  
     },
+    /// The arguments are the positional ones followed by the values of the
+    /// keyword arguments `site` names.
     Call {
         callee: Box<Expr>,
-        paren: Token,
+        site: Rc<CallSite>,
         arguments: Vec<Expr>
     },
     Literal {
@@ -65,60 +99,296 @@ pub enum Expr {
     Array {
         value: Vec<Expr>
     },
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>
+    },
+    SetIndex {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        value: Box<Expr>
+    },
+    /// `object.name`, a member of a native module.
+    Get {
+        object: Box<Expr>,
+        name: Token
+    },
+    /// `[start..end]`, with `dots` spanning the `..`.
     Range {
         start: Box<Expr>,
-        end: Box<Expr>,
-        line: u32
+        dots: Token,
+        end: Box<Expr>
     }
 }
 
-#[derive(Debug, Clone)]
+/// An operator in a chain of binary and logical operators, with the operand
+/// on its right.
+pub struct Link<'a> {
+    pub operator: &'a Token,
+    pub right: &'a Expr,
+    pub logical: bool
+}
+
+/// How many links a chain may have for passes to recurse down its left
+/// side as for any other operand.
+const SHORT_CHAIN: usize = 16;
+
+impl Expr {
+    /// Splits a chain of binary and logical operators, which nests to the
+    /// left as `a + b - c` is `(a + b) - c`, into its first operand and the
+    /// links after it. Passes walk the links in a loop instead of recursing
+    /// down the left, so a long chain doesn't take a deep stack.
+    pub fn chain(&self) -> (&Expr, Vec<Link<'_>>) {
+        let mut links = Vec::new();
+        let mut first = self;
+        while let Some((left, link)) = first.link() {
+            links.push(link);
+            first = left;
+        }
+
+        links.reverse();
+        (first, links)
+    }
+
+    /// Like `chain`, the first operand of a chain and the right operands of
+    /// its links, for passes that rewrite them.
+    pub fn chain_mut(&mut self) -> (&mut Expr, Vec<&mut Expr>) {
+        let mut rights = Vec::new();
+        let mut expr = self;
+        let first = loop {
+            match expr {
+                Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                    rights.push(right.as_mut());
+                    expr = left;
+                }
+                first => break first
+            }
+        };
+
+        rights.reverse();
+        (first, rights)
+    }
+
+    /// Whether this is a chain too long to recurse down.
+    pub fn is_long_chain(&self) -> bool {
+        let mut expr = self;
+        for _ in 0..SHORT_CHAIN {
+            match expr.link() {
+                Some((left, _)) => expr = left,
+                None => return false
+            }
+        }
+        expr.link().is_some()
+    }
+
+    /// The left operand and the link of a binary or logical operator.
+    fn link(&self) -> Option<(&Expr, Link<'_>)> {
+        match self {
+            Expr::Binary { left, operator, right } => Some((left, Link { operator, right, logical: false })),
+            Expr::Logical { left, operator, right } => Some((left, Link { operator, right, logical: true })),
+            _ => None
+        }
+    }
+
+    /// The expression without any surrounding parentheses. A `return` whose
+    /// unparenthesized value is a call is a tail call.
+    pub fn unparenthesized(&self) -> &Expr {
+        match self {
+            Expr::Grouping { expression } => expression.unparenthesized(),
+            expr => expr
+        }
+    }
+}
+
+/// Where a call is, for reporting errors at its closing parenthesis, and
+/// the names of its keyword arguments: `sep` in `print(a, b, sep: ", ")`.
+/// Only native functions take keyword arguments.
+#[derive(Debug)]
+pub struct CallSite {
+    pub paren: Token,
+    pub keywords: Vec<Token>,
+    /// The source text of each argument, kept only for calls to `dbg`.
+    pub source: Vec<Box<str>>
+}
+
+impl CallSite {
+    /// How many of `count` arguments are positional.
+    #[inline]
+    pub fn positional(&self, count: usize) -> usize {
+        count - self.keywords.len()
+    }
+
+    /// The value of the keyword argument `name` among the call's `arguments`.
+    pub fn keyword<'a>(&self, arguments: &'a [AliceObject], name: &str) -> Option<&'a AliceObject> {
+        let start = self.positional(arguments.len());
+        self.keywords.iter().position(|keyword| keyword.symbol().as_str() == name).map(|index| &arguments[start + index])
+    }
+
+    /// Fails on the first keyword argument, for callees that take none.
+    pub fn positional_only(&self) -> Result<(), AliceError> {
+        match self.keywords.first() {
+            Some(keyword) => Err(AliceError::runtime(format!("Unexpected keyword argument '{}'.", keyword.symbol()), keyword)),
+            None => Ok(())
+        }
+    }
+}
+
+/// Lexical address of a local variable, filled in by the `Resolver`:
+/// `depth` environments up the chain, at index `slot`. Variables left
+/// unbound are globals and are looked up by name.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Binding {
+    pub depth: usize,
+    pub slot: usize
+}
+
+/// A runtime value.
+///
+/// Values are two words wide: numbers, booleans and short strings are held
+/// inline, everything else behind an `Rc`, so cloning a value never copies
+/// more than a pointer.
+///
+/// Arrays have reference semantics: assigning an array, passing it to a
+/// function or storing it in another array shares the same storage, so a
+/// mutation through any alias (`a[0] = 1`) is visible through all of them,
+/// and `==` on arrays compares identity. Evaluating an array literal always
+/// creates a new array.
+#[derive(Clone)]
 pub enum AliceObject {
-    Array(Vec<AliceObject>),
-    Range(i64, i64),
-    String(String),
+    Function(Rc<AliceFunction>),
+    Native(Rc<NativeFunction>),
+    Module(Rc<NativeModule>),
+    Array(Rc<Array>),
+    Range(Rc<AliceRange>),
+    String(AliceString),
     F64(f64),
     I64(i64),
     Boolean(bool),
     Nil
 }
 
+#[cfg(target_pointer_width = "64")]
+const _: () = assert!(std::mem::size_of::<AliceObject>() == 16);
+
+/// The elements of an array.
+///
+/// Dropping an array drops the arrays nested in it one after another rather
+/// than one inside the other, so even a very deeply nested array can be
+/// freed without running out of stack.
+pub struct Array(RefCell<Vec<AliceObject>>);
+
+impl Array {
+    #[inline]
+    pub fn new(values: Vec<AliceObject>) -> Array {
+        Array(RefCell::new(values))
+    }
+}
+
+impl Deref for Array {
+    type Target = RefCell<Vec<AliceObject>>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Drop for Array {
+    fn drop(&mut self) {
+        let mut pending = std::mem::take(self.0.get_mut());
+        while let Some(value) = pending.pop() {
+            // An array only referenced from here is emptied before it goes,
+            // so its own drop has nothing left to recurse into.
+            if let AliceObject::Array(array) = value {
+                if let Ok(mut array) = Rc::try_unwrap(array) {
+                    pending.append(array.0.get_mut());
+                }
+            }
+        }
+    }
+}
+
+/// The half-open integer range `start..end`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AliceRange {
+    pub start: i64,
+    pub end: i64
+}
+
+impl AliceObject {
+    #[inline]
+    pub fn array(values: Vec<AliceObject>) -> AliceObject {
+        AliceObject::Array(Rc::new(Array::new(values)))
+    }
+
+    /// The name of the value's type, for error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            AliceObject::Function(_) | AliceObject::Native(_) => "function",
+            AliceObject::Module(_) => "module",
+            AliceObject::Array(_) => "array",
+            AliceObject::Range(_) => "range",
+            AliceObject::String(_) => "string",
+            AliceObject::F64(_) => "f64",
+            AliceObject::I64(_) => "i64",
+            AliceObject::Boolean(_) => "bool",
+            AliceObject::Nil => "nil"
+        }
+    }
+}
+
+impl Debug for AliceObject {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AliceObject::Function(function) => f.debug_tuple("Function").field(function).finish(),
+            AliceObject::Native(function) => f.debug_tuple("Native").field(function).finish(),
+            AliceObject::Module(module) => f.debug_tuple("Module").field(module).finish(),
+            AliceObject::Array(array) => f.debug_tuple("Array").field(&array.borrow()).finish(),
+            AliceObject::Range(range) => f.debug_tuple("Range").field(&range.start).field(&range.end).finish(),
+            AliceObject::String(str) => f.debug_tuple("String").field(str).finish(),
+            AliceObject::F64(num) => f.debug_tuple("F64").field(num).finish(),
+            AliceObject::I64(num) => f.debug_tuple("I64").field(num).finish(),
+            AliceObject::Boolean(bool) => f.debug_tuple("Boolean").field(bool).finish(),
+            AliceObject::Nil => write!(f, "Nil")
+        }
+    }
+}
+
 pub trait VisitStmt<R> {
-    fn visit_println_stmt(&mut self, expression: Option<Expr>) -> Result<R, AliceError>;
-    fn visit_return_stmt(&mut self, keyword: Token, value: Option<Expr>) -> Result<R, AliceError>;
-    fn visit_var_stmt(&mut self, name: Token, initializer: Option<Expr>) -> Result<R, AliceError>;
-    fn visit_block_stmt(&mut self, statements: Vec<Stmt>) -> Result<R, AliceError>;
-    fn visit_fn_stmt(&mut self, name: Token, params: Vec<Token>, body: Vec<Stmt>) -> Result<R, AliceError>;
-    fn visit_if_stmt(&mut self, condition: Expr, then_branch: Stmt, else_branch: Option<Box<Stmt>>) -> Result<R, AliceError>;
-    fn visit_for_stmt(&mut self, value: Token, expression: Expr, body: Vec<Stmt>) -> Result<R, AliceError>;
-    fn visit_expression_stmt(&mut self, expression: Expr) -> Result<R, AliceError>;
-
-    fn execute(&mut self, stmt: Stmt) -> Result<R, AliceError> {
+    fn visit_return_stmt(&mut self, keyword: &Token, value: Option<&Expr>) -> Result<R, AliceError>;
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) -> Result<R, AliceError>;
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) -> Result<R, AliceError>;
+    fn visit_fn_stmt(&mut self, name: &Token, params: &[Token], body: &Rc<Vec<Stmt>>) -> Result<R, AliceError>;
+    fn visit_if_stmt(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) -> Result<R, AliceError>;
+    fn visit_for_stmt(&mut self, value: &Token, expression: &Expr, body: &[Stmt]) -> Result<R, AliceError>;
+    fn visit_expression_stmt(&mut self, expression: &Expr) -> Result<R, AliceError>;
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<R, AliceError> {
         match stmt {
-            Stmt::Println { expression } => self.visit_println_stmt(expression),
-            Stmt::Return { keyword, value } => self.visit_return_stmt(keyword, value),
-            Stmt::Var { name, initializer } => self.visit_var_stmt(name, initializer),
+            Stmt::Return { keyword, value } => self.visit_return_stmt(keyword, value.as_ref()),
+            Stmt::Var { name, initializer, .. } => self.visit_var_stmt(name, initializer.as_ref()),
             Stmt::Block { statements } => self.visit_block_stmt(statements),
-            Stmt::Fn { name, params, body } => self.visit_fn_stmt(name, params, body),
-            Stmt::If { condition, then_branch, else_branch } => self.visit_if_stmt(condition, *then_branch, else_branch),
+            Stmt::Fn { name, params, body, .. } => self.visit_fn_stmt(name, params, body),
+            Stmt::If { condition, then_branch, else_branch } => self.visit_if_stmt(condition, then_branch, else_branch.as_deref()),
             Stmt::For { value, expression, body } => self.visit_for_stmt(value, expression, body),
             Stmt::Expression { expression } => self.visit_expression_stmt(expression)
         }
     }
 }
 
+/// A statement of a parsed script.
 #[derive(Debug, Clone)]
 pub enum Stmt {
-    Println {
-        expression: Option<Expr>
-    },
     Return {
         keyword: Token,
         value: Option<Expr>
     },
     Var {
         name: Token,
-        initializer: Option<Expr>
+        initializer: Option<Expr>,
+        doc: Option<Box<str>>
     },
     Block {
         statements: Vec<Stmt>
@@ -126,7 +396,8 @@ pub enum Stmt {
     Fn {
         name: Token,
         params: Vec<Token>,
-        body: Vec<Stmt>
+        body: Rc<Vec<Stmt>>,
+        doc: Option<Box<str>>
     },
     If {
         condition: Expr,
@@ -141,4 +412,26 @@ pub enum Stmt {
     Expression {
         expression: Expr
     }
-}
\ No newline at end of file
+}
+
+impl Stmt {
+    /// The `///` comment written directly above a `fn` or `let`
+    /// declaration, one line per comment line, without the slashes.
+    pub fn doc(&self) -> Option<&str> {
+        match self {
+            Stmt::Var { doc, .. } |
+            Stmt::Fn { doc, .. } => doc.as_deref(),
+            _ => None
+        }
+    }
+
+    /// The expression of an expression statement that is not an
+    /// assignment, i.e. one whose value a REPL should echo.
+    pub fn bare_expression(&self) -> Option<&Expr> {
+        match self {
+            Stmt::Expression { expression: Expr::Assign { .. } | Expr::SetIndex { .. } } => None,
+            Stmt::Expression { expression } => Some(expression),
+            _ => None
+        }
+    }
+}
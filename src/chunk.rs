@@ -0,0 +1,116 @@
+use std::rc::Rc;
+
+use crate::{ast::{AliceObject, CallSite}, token::Token};
+
+/// A single VM instruction. Operands index into the owning `Chunk`'s
+/// constant, token and function pools; jump targets are absolute offsets
+/// into `Chunk::code`.
+#[derive(Debug, Clone, Copy)]
+pub enum Op {
+    Constant(u32),
+    Nil,
+    True,
+    False,
+    Pop,
+
+    /// A local in a slot of the current frame.
+    GetLocal(u32),
+    SetLocal(u32),
+    /// Drops the given number of locals off the top of the stack.
+    PopLocals(u32),
+    /// A captured local, by how many environments out it is and its slot.
+    GetCaptured(u32, u32),
+    SetCaptured(u32, u32),
+    DefineCaptured,
+    GetGlobal(u32),
+    SetGlobal(u32),
+    DefineGlobal(u32),
+    PushScope,
+    PopScope,
+
+    Unary(u32),
+    Binary(u32),
+    Array(u32),
+    GetIndex(u32),
+    SetIndex(u32),
+    GetMember(u32),
+    Range(u32),
+
+    Jump(u32),
+    JumpIfFalse(u32),
+    JumpIfFalseKeep(u32),
+    JumpIfTrueKeep(u32),
+    ForPrepare(u32),
+    ForNext(u32),
+
+    Closure(u32),
+    Call(u32, u32),
+    TailCall(u32, u32),
+    Return
+}
+
+/// Compiled form of a function body (or of the whole script).
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<Op>,
+    pub constants: Vec<AliceObject>,
+    /// Tokens that instructions report runtime errors against.
+    pub tokens: Vec<Token>,
+    /// The call sites of `Op::Call` and `Op::TailCall`.
+    pub calls: Vec<Rc<CallSite>>,
+    pub functions: Vec<Rc<Prototype>>
+}
+
+/// A function as it appears in the constant pool, before `Op::Closure`
+/// captures the environment it was declared in.
+#[derive(Debug)]
+pub struct Prototype {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub chunk: Rc<Chunk>
+}
+
+impl Chunk {
+    #[inline]
+    pub fn emit(&mut self, op: Op) -> usize {
+        self.code.push(op);
+        self.code.len() - 1
+    }
+
+    #[inline]
+    pub fn constant(&mut self, value: AliceObject) -> u32 {
+        self.constants.push(value);
+        (self.constants.len() - 1) as u32
+    }
+
+    #[inline]
+    pub fn token(&mut self, token: Token) -> u32 {
+        self.tokens.push(token);
+        (self.tokens.len() - 1) as u32
+    }
+
+    #[inline]
+    pub fn call(&mut self, site: Rc<CallSite>) -> u32 {
+        self.calls.push(site);
+        (self.calls.len() - 1) as u32
+    }
+
+    #[inline]
+    pub fn function(&mut self, prototype: Prototype) -> u32 {
+        self.functions.push(Rc::new(prototype));
+        (self.functions.len() - 1) as u32
+    }
+
+    /// Points the jump at `offset` to the next instruction to be emitted.
+    pub fn patch(&mut self, offset: usize) {
+        let target = self.code.len() as u32;
+        self.code[offset] = match self.code[offset] {
+            Op::Jump(_) => Op::Jump(target),
+            Op::JumpIfFalse(_) => Op::JumpIfFalse(target),
+            Op::JumpIfFalseKeep(_) => Op::JumpIfFalseKeep(target),
+            Op::JumpIfTrueKeep(_) => Op::JumpIfTrueKeep(target),
+            Op::ForNext(_) => Op::ForNext(target),
+            op => op
+        };
+    }
+}
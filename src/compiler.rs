@@ -0,0 +1,528 @@
+use std::{collections::HashSet, rc::Rc};
+
+use crate::{ast::{Expr, Stmt, AliceObject, Binding, CallSite, VisitExpr, VisitStmt}, chunk::{Chunk, Op, Prototype}, error::AliceError, token::{Token, TokenType}};
+
+/// Compiles a resolved program into bytecode for the `Vm`.
+///
+/// Locals live in stack slots of their function's frame. Only the locals
+/// that a nested function refers to are captured: they go into an
+/// `Environment`, one for each scope that declares any, chained like the
+/// tree-walking interpreter's, so closures behave identically under both
+/// engines. The `Resolver`'s (depth, slot) bindings, which count every
+/// scope, are translated to one or the other.
+#[derive(Default)]
+pub struct Compiler {
+    chunk: Chunk,
+    /// The local scopes around the code being compiled, innermost last,
+    /// including those of the functions it is nested in.
+    scopes: Vec<Scope>,
+    /// Stack slots in use in the current frame, by locals and by the
+    /// collections `for` loops iterate.
+    slots: u32,
+    /// The declarations of captured locals.
+    captured: HashSet<*const Token>
+}
+
+struct Scope {
+    /// In the order the `Resolver` numbers them.
+    locals: Vec<Local>,
+    /// Whether the scope has an `Environment` for its captured locals.
+    environment: bool
+}
+
+#[derive(Clone, Copy)]
+enum Local {
+    /// A slot in the frame.
+    Stack(u32),
+    /// A slot in the scope's `Environment`.
+    Captured(u32)
+}
+
+impl Compiler {
+    #[inline]
+    pub fn new() -> Compiler {
+        Compiler::default()
+    }
+
+    pub fn compile(mut self, statements: &[Stmt]) -> Result<Chunk, AliceError> {
+        self.captured = Captures::find(statements);
+        for stmt in statements {
+            self.execute(stmt)?;
+        }
+        self.chunk.emit(Op::Nil);
+        self.chunk.emit(Op::Return);
+
+        Ok(self.chunk)
+    }
+
+    /// Compiles one input of an interactive session. If the last statement
+    /// is a bare expression, the chunk returns its value instead of `nil`.
+    pub fn compile_line(mut self, statements: &[Stmt]) -> Result<Chunk, AliceError> {
+        let Some(expression) = statements.last().and_then(Stmt::bare_expression) else {
+            return self.compile(statements);
+        };
+
+        self.captured = Captures::find(statements);
+        for stmt in &statements[..statements.len() - 1] {
+            self.execute(stmt)?;
+        }
+        self.evaluate(expression)?;
+        self.chunk.emit(Op::Return);
+
+        Ok(self.chunk)
+    }
+
+    fn function(&mut self, name: &Token, params: &[Token], body: &[Stmt]) -> Result<u32, AliceError> {
+        let enclosing = std::mem::take(&mut self.chunk);
+        // Slot 0 holds the function being called.
+        let slots = std::mem::replace(&mut self.slots, 1);
+
+        let result = self.body(params, body);
+        self.scopes.pop();
+        self.slots = slots;
+        let chunk = std::mem::replace(&mut self.chunk, enclosing);
+        result?;
+
+        let prototype = Prototype { name: name.clone(), params: params.to_vec(), chunk: Rc::new(chunk) };
+        Ok(self.chunk.function(prototype))
+    }
+
+    fn body(&mut self, params: &[Token], body: &[Stmt]) -> Result<(), AliceError> {
+        self.begin_scope(params.iter().chain(declared(body)));
+        // The arguments are already in the slots after the function. Those
+        // that are captured are copied into the environment, though their
+        // slots stay taken.
+        for param in params {
+            if let Local::Captured(_) = self.local(param) {
+                self.chunk.emit(Op::GetLocal(self.slots));
+                self.chunk.emit(Op::DefineCaptured);
+                self.slots += 1;
+            }
+        }
+
+        for stmt in body {
+            self.execute(stmt)?;
+        }
+        self.chunk.emit(Op::Nil);
+        self.chunk.emit(Op::Return);
+        Ok(())
+    }
+
+    /// Opens a scope, with an `Environment` if any of the locals `declared`
+    /// in it are captured.
+    fn begin_scope<'a>(&mut self, mut declared: impl Iterator<Item = &'a Token>) {
+        let environment = declared.any(|name| self.captured.contains(&(name as *const Token)));
+        if environment {
+            self.chunk.emit(Op::PushScope);
+        }
+        self.scopes.push(Scope { locals: Vec::new(), environment });
+    }
+
+    fn end_scope(&mut self) {
+        let scope = self.scopes.pop().expect("scopes are balanced");
+        let count = scope.locals.iter().filter(|local| matches!(local, Local::Stack(_))).count() as u32;
+        if count > 0 {
+            self.chunk.emit(Op::PopLocals(count));
+            self.slots -= count;
+        }
+        if scope.environment {
+            self.chunk.emit(Op::PopScope);
+        }
+    }
+
+    #[inline]
+    fn scope(&mut self) -> &mut Scope {
+        self.scopes.last_mut().expect("locals are declared in a scope")
+    }
+
+    /// Adds the local `name` to the current scope, returning where it lives.
+    fn local(&mut self, name: &Token) -> Local {
+        let local = match self.captured.contains(&(name as *const Token)) {
+            true => Local::Captured(self.scope().locals.iter().filter(|local| matches!(local, Local::Captured(_))).count() as u32),
+            false => {
+                self.slots += 1;
+                Local::Stack(self.slots - 1)
+            }
+        };
+        self.scope().locals.push(local);
+        local
+    }
+
+    /// Declares `name` with the value on top of the stack.
+    fn define(&mut self, name: &Token) {
+        if self.scopes.is_empty() {
+            let name = self.chunk.token(name.clone());
+            self.chunk.emit(Op::DefineGlobal(name));
+            return;
+        }
+
+        // A local in the stack is already where the value is.
+        if let Local::Captured(_) = self.local(name) {
+            self.chunk.emit(Op::DefineCaptured);
+        }
+    }
+
+    /// Where the local with `binding` lives: a slot in the frame, or the
+    /// `Environment` it is in, counted from the innermost, and its slot there.
+    fn resolve(&self, Binding { depth, slot }: Binding) -> Result<u32, (u32, u32)> {
+        let target = self.scopes.len() - 1 - depth;
+        match self.scopes[target].locals[slot] {
+            Local::Stack(slot) => Ok(slot),
+            Local::Captured(slot) => Err((self.scopes[target + 1..].iter().filter(|scope| scope.environment).count() as u32, slot))
+        }
+    }
+
+    /// Pushes the callee followed by the arguments of a call.
+    fn arguments(&mut self, callee: &Expr, arguments: &[Expr]) -> Result<(), AliceError> {
+        self.evaluate(callee)?;
+        for argument in arguments {
+            self.evaluate(argument)?;
+        }
+        Ok(())
+    }
+
+    fn scoped(&mut self, statements: &[Stmt]) -> Result<(), AliceError> {
+        for stmt in statements {
+            self.execute(stmt)?;
+        }
+        self.end_scope();
+        Ok(())
+    }
+}
+
+impl VisitExpr<()> for Compiler {
+    fn visit_grouping_expr(&mut self, expression: &Expr) -> Result<(), AliceError> {
+        self.evaluate(expression)
+    }
+
+    fn visit_variable_expr(&mut self, name: &Token, binding: Option<Binding>) -> Result<(), AliceError> {
+        match binding.map(|binding| self.resolve(binding)) {
+            Some(Ok(slot)) => self.chunk.emit(Op::GetLocal(slot)),
+            Some(Err((depth, slot))) => self.chunk.emit(Op::GetCaptured(depth, slot)),
+            None => {
+                let name = self.chunk.token(name.clone());
+                self.chunk.emit(Op::GetGlobal(name))
+            }
+        };
+        Ok(())
+    }
+
+    fn visit_assign_expr(&mut self, name: &Token, value: &Expr, binding: Option<Binding>) -> Result<(), AliceError> {
+        self.evaluate(value)?;
+        match binding.map(|binding| self.resolve(binding)) {
+            Some(Ok(slot)) => self.chunk.emit(Op::SetLocal(slot)),
+            Some(Err((depth, slot))) => self.chunk.emit(Op::SetCaptured(depth, slot)),
+            None => {
+                let name = self.chunk.token(name.clone());
+                self.chunk.emit(Op::SetGlobal(name))
+            }
+        };
+        Ok(())
+    }
+
+    fn visit_unary_expr(&mut self, operator: &Token, value: &Expr) -> Result<(), AliceError> {
+        self.evaluate(value)?;
+        let operator = self.chunk.token(operator.clone());
+        self.chunk.emit(Op::Unary(operator));
+        Ok(())
+    }
+
+    fn visit_binary_expr(&mut self, _left: (), operator: &Token, right: &Expr) -> Result<(), AliceError> {
+        self.evaluate(right)?;
+        let operator = self.chunk.token(operator.clone());
+        self.chunk.emit(Op::Binary(operator));
+        Ok(())
+    }
+
+    fn visit_logical_expr(&mut self, _left: (), operator: &Token, right: &Expr) -> Result<(), AliceError> {
+        let jump = if operator.r#type == TokenType::Or {
+            self.chunk.emit(Op::JumpIfTrueKeep(0))
+        } else {
+            self.chunk.emit(Op::JumpIfFalseKeep(0))
+        };
+        self.chunk.emit(Op::Pop);
+        self.evaluate(right)?;
+        self.chunk.patch(jump);
+
+        Ok(())
+    }
+
+    fn visit_call_expr(&mut self, callee: &Expr, site: &Rc<CallSite>, arguments: &[Expr]) -> Result<(), AliceError> {
+        self.arguments(callee, arguments)?;
+        let site = self.chunk.call(site.clone());
+        self.chunk.emit(Op::Call(arguments.len() as u32, site));
+        Ok(())
+    }
+
+    fn visit_literal_expr(&mut self, value: &AliceObject) -> Result<(), AliceError> {
+        match value {
+            AliceObject::Nil => self.chunk.emit(Op::Nil),
+            AliceObject::Boolean(true) => self.chunk.emit(Op::True),
+            AliceObject::Boolean(false) => self.chunk.emit(Op::False),
+            value => {
+                let constant = self.chunk.constant(value.clone());
+                self.chunk.emit(Op::Constant(constant))
+            }
+        };
+        Ok(())
+    }
+
+    fn visit_array_expr(&mut self, list: &[Expr]) -> Result<(), AliceError> {
+        let count = list.len() as u32;
+        for expr in list {
+            self.evaluate(expr)?;
+        }
+        self.chunk.emit(Op::Array(count));
+        Ok(())
+    }
+
+    fn visit_index_expr(&mut self, object: &Expr, bracket: &Token, index: &Expr) -> Result<(), AliceError> {
+        self.evaluate(object)?;
+        self.evaluate(index)?;
+        let bracket = self.chunk.token(bracket.clone());
+        self.chunk.emit(Op::GetIndex(bracket));
+        Ok(())
+    }
+
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> Result<(), AliceError> {
+        self.evaluate(object)?;
+        let name = self.chunk.token(name.clone());
+        self.chunk.emit(Op::GetMember(name));
+        Ok(())
+    }
+
+    fn visit_set_index_expr(&mut self, object: &Expr, bracket: &Token, index: &Expr, value: &Expr) -> Result<(), AliceError> {
+        self.evaluate(object)?;
+        self.evaluate(index)?;
+        self.evaluate(value)?;
+        let bracket = self.chunk.token(bracket.clone());
+        self.chunk.emit(Op::SetIndex(bracket));
+        Ok(())
+    }
+
+    fn visit_range_expr(&mut self, start: &Expr, dots: &Token, end: &Expr) -> Result<(), AliceError> {
+        self.evaluate(start)?;
+        self.evaluate(end)?;
+        let dots = self.chunk.token(dots.clone());
+        self.chunk.emit(Op::Range(dots));
+        Ok(())
+    }
+}
+
+impl VisitStmt<()> for Compiler {
+    fn visit_return_stmt(&mut self, _keyword: &Token, value: Option<&Expr>) -> Result<(), AliceError> {
+        if let Some(Expr::Call { callee, site, arguments }) = value.map(Expr::unparenthesized) {
+            self.arguments(callee, arguments)?;
+            let site = self.chunk.call(site.clone());
+            self.chunk.emit(Op::TailCall(arguments.len() as u32, site));
+            // Only reached when the callee is native.
+            self.chunk.emit(Op::Return);
+            return Ok(());
+        }
+
+        match value {
+            Some(value) => self.evaluate(value)?,
+            None => {
+                self.chunk.emit(Op::Nil);
+            }
+        }
+        self.chunk.emit(Op::Return);
+        Ok(())
+    }
+
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) -> Result<(), AliceError> {
+        match initializer {
+            Some(expr) => self.evaluate(expr)?,
+            None => {
+                self.chunk.emit(Op::Nil);
+            }
+        }
+        self.define(name);
+        Ok(())
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) -> Result<(), AliceError> {
+        self.begin_scope(declared(statements));
+        self.scoped(statements)
+    }
+
+    fn visit_fn_stmt(&mut self, name: &Token, params: &[Token], body: &Rc<Vec<Stmt>>) -> Result<(), AliceError> {
+        // The name is in scope in the body, for recursion.
+        let local = (!self.scopes.is_empty()).then(|| self.local(name));
+        let function = self.function(name, params, body)?;
+        self.chunk.emit(Op::Closure(function));
+        match local {
+            None => {
+                let name = self.chunk.token(name.clone());
+                self.chunk.emit(Op::DefineGlobal(name));
+            }
+            Some(Local::Captured(_)) => {
+                self.chunk.emit(Op::DefineCaptured);
+            }
+            Some(Local::Stack(_)) => ()
+        }
+        Ok(())
+    }
+
+    fn visit_if_stmt(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) -> Result<(), AliceError> {
+        self.evaluate(condition)?;
+
+        let then_jump = self.chunk.emit(Op::JumpIfFalse(0));
+        self.execute(then_branch)?;
+
+        if let Some(else_branch) = else_branch {
+            let else_jump = self.chunk.emit(Op::Jump(0));
+            self.chunk.patch(then_jump);
+            self.execute(else_branch)?;
+            self.chunk.patch(else_jump);
+        } else {
+            self.chunk.patch(then_jump);
+        }
+
+        Ok(())
+    }
+
+    fn visit_for_stmt(&mut self, value: &Token, expression: &Expr, body: &[Stmt]) -> Result<(), AliceError> {
+        self.evaluate(expression)?;
+
+        let token = self.chunk.token(value.clone());
+        self.chunk.emit(Op::ForPrepare(token));
+        self.slots += 2;
+
+        let start = self.chunk.code.len() as u32;
+        let exit = self.chunk.emit(Op::ForNext(0));
+        self.begin_scope(std::iter::once(value).chain(declared(body)));
+        self.define(value);
+        self.scoped(body)?;
+        self.chunk.emit(Op::Jump(start));
+        self.chunk.patch(exit);
+
+        // Drop the iterated collection and the cursor ForPrepare pushed.
+        self.chunk.emit(Op::Pop);
+        self.chunk.emit(Op::Pop);
+        self.slots -= 2;
+
+        Ok(())
+    }
+
+    fn visit_expression_stmt(&mut self, expression: &Expr) -> Result<(), AliceError> {
+        self.evaluate(expression)?;
+        self.chunk.emit(Op::Pop);
+        Ok(())
+    }
+}
+
+/// The names `statements` declare directly, in their own scope.
+fn declared(statements: &[Stmt]) -> impl Iterator<Item = &Token> {
+    statements.iter().filter_map(|stmt| match stmt {
+        Stmt::Var { name, .. } |
+        Stmt::Fn { name, .. } => Some(name),
+        _ => None
+    })
+}
+
+/// Finds the locals a function nested in their scope refers to, walking
+/// the scopes as the `Resolver` did. Declarations are told apart by the
+/// address of their name in the syntax tree.
+#[derive(Default)]
+struct Captures {
+    /// The declarations in each scope and the function it belongs to.
+    scopes: Vec<(Vec<*const Token>, usize)>,
+    function: usize,
+    captured: HashSet<*const Token>
+}
+
+impl Captures {
+    fn find(statements: &[Stmt]) -> HashSet<*const Token> {
+        let mut captures = Captures::default();
+        captures.stmts(statements);
+        captures.captured
+    }
+
+    fn stmts(&mut self, statements: &[Stmt]) {
+        statements.iter().for_each(|stmt| self.stmt(stmt));
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Return { value, .. } => value.iter().for_each(|value| self.expr(value)),
+            Stmt::Var { name, initializer, .. } => {
+                self.declare(name);
+                initializer.iter().for_each(|initializer| self.expr(initializer));
+            }
+            Stmt::Block { statements } => self.scoped(&[], statements),
+            Stmt::Fn { name, params, body, .. } => {
+                self.declare(name);
+                self.function += 1;
+                self.scoped(params, body);
+                self.function -= 1;
+            }
+            Stmt::If { condition, then_branch, else_branch } => {
+                self.expr(condition);
+                self.stmt(then_branch);
+                else_branch.iter().for_each(|else_branch| self.stmt(else_branch));
+            }
+            Stmt::For { value, expression, body } => {
+                self.expr(expression);
+                self.scoped(std::slice::from_ref(value), body);
+            }
+            Stmt::Expression { expression } => self.expr(expression)
+        }
+    }
+
+    fn scoped(&mut self, names: &[Token], statements: &[Stmt]) {
+        self.scopes.push((names.iter().map(|name| name as *const Token).collect(), self.function));
+        self.stmts(statements);
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        if let Some((names, _)) = self.scopes.last_mut() {
+            names.push(name);
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Variable { binding, .. } => self.refer(*binding),
+            Expr::Assign { value, binding, .. } => {
+                self.expr(value);
+                self.refer(*binding);
+            }
+            Expr::Grouping { expression: value } |
+            Expr::Unary { value, .. } |
+            Expr::Get { object: value, .. } => self.expr(value),
+            Expr::Binary { .. } | Expr::Logical { .. } => {
+                let (first, links) = expr.chain();
+                self.expr(first);
+                links.iter().for_each(|link| self.expr(link.right));
+            }
+            Expr::Index { object: left, index: right, .. } |
+            Expr::Range { start: left, end: right, .. } => {
+                self.expr(left);
+                self.expr(right);
+            }
+            Expr::SetIndex { object, index, value, .. } => {
+                self.expr(object);
+                self.expr(index);
+                self.expr(value);
+            }
+            Expr::Call { callee, arguments, .. } => {
+                self.expr(callee);
+                arguments.iter().for_each(|argument| self.expr(argument));
+            }
+            Expr::Array { value } => value.iter().for_each(|value| self.expr(value)),
+            Expr::Literal { .. } => ()
+        }
+    }
+
+    /// Marks the local `binding` refers to as captured if it belongs to an
+    /// enclosing function.
+    fn refer(&mut self, binding: Option<Binding>) {
+        let Some(Binding { depth, slot }) = binding else { return };
+        let (names, function) = &self.scopes[self.scopes.len() - 1 - depth];
+        if *function != self.function {
+            self.captured.insert(names[slot]);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::{fmt::Write, rc::Rc};
+
+use crate::ast::{AliceObject, Array};
+
+// How values are written out, in Alice's own syntax: `[1, "two", 3.5]`,
+// `0..7`, `<fn f>`. Strings are written as they are at the top level and
+// quoted inside arrays, so `["a, b"]` and `["a", "b"]` stay distinguishable;
+// quoted strings use the escapes the scanner reads, so `["say \"hi\"\n"]`
+// can be pasted back into a script. An `f64` always has a fractional part or
+// an exponent, so `2.0` doesn't pass for the `i64` 2. An array that contains
+// itself is written as `[...]` where it recurs, and so are arrays nested
+// more than `MAX_DEPTH` deep, which writing would recurse too far for.
+
+/// How `print` and friends write a value.
+pub fn display(value: &AliceObject) -> String {
+    match value {
+        AliceObject::String(str) => str.to_string(),
+        value => debug(value)
+    }
+}
+
+/// Like `display`, but with strings quoted, as they are in arrays.
+pub fn debug(value: &AliceObject) -> String {
+    let mut text = String::new();
+    Writer::default().flat(&mut text, value);
+    text
+}
+
+/// Like `debug`, but arrays that don't fit in `width` columns are spread
+/// over several lines with an element per line.
+pub fn pretty(value: &AliceObject, width: usize) -> String {
+    let mut text = String::new();
+    Writer::default().pretty(&mut text, value, 0, width);
+    text
+}
+
+/// How an `f64` is written: with at least one decimal, or in scientific
+/// notation if it is very large or very small.
+pub fn float(num: f64) -> String {
+    let magnitude = num.abs();
+    if num.is_finite() && num != 0.0 && !(1e-5..1e16).contains(&magnitude) {
+        return format!("{:e}", num);
+    }
+
+    let text = num.to_string();
+    match num.is_finite() && !text.contains('.') {
+        true => text + ".0",
+        false => text
+    }
+}
+
+/// Writes `str` as a string literal.
+fn quote(text: &mut String, str: &str) {
+    text.push('"');
+    for c in str.chars() {
+        match c {
+            '"' => text.push_str("\\\""),
+            '\\' => text.push_str("\\\\"),
+            '\n' => text.push_str("\\n"),
+            '\r' => text.push_str("\\r"),
+            '\t' => text.push_str("\\t"),
+            '\0' => text.push_str("\\0"),
+            c if c.is_control() => write!(text, "\\u{{{:x}}}", c as u32).expect("writing to a String does not fail"),
+            c => text.push(c)
+        }
+    }
+    text.push('"');
+}
+
+const INDENT: usize = 4;
+
+/// How deeply nested arrays are written out.
+const MAX_DEPTH: usize = 100;
+
+/// Writes values, keeping track of the arrays it is inside of.
+#[derive(Default)]
+struct Writer {
+    open: Vec<*const Array>
+}
+
+impl Writer {
+    fn flat(&mut self, text: &mut String, value: &AliceObject) {
+        match value {
+            AliceObject::Array(array) => self.array(text, array, |writer, text, values| {
+                text.push('[');
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        text.push_str(", ");
+                    }
+                    writer.flat(text, value);
+                }
+                text.push(']');
+            }),
+            AliceObject::Range(range) => write!(text, "{}..{}", range.start, range.end).expect("writing to a String does not fail"),
+            AliceObject::String(str) => quote(text, str),
+            AliceObject::Function(function) => write!(text, "{:?}", function).expect("writing to a String does not fail"),
+            AliceObject::Native(function) => write!(text, "{:?}", function).expect("writing to a String does not fail"),
+            AliceObject::Module(module) => write!(text, "{:?}", module).expect("writing to a String does not fail"),
+            AliceObject::F64(num) => text.push_str(&float(*num)),
+            AliceObject::I64(num) => write!(text, "{}", num).expect("writing to a String does not fail"),
+            AliceObject::Boolean(bool) => write!(text, "{}", bool).expect("writing to a String does not fail"),
+            AliceObject::Nil => text.push_str("nil")
+        }
+    }
+
+    fn pretty(&mut self, text: &mut String, value: &AliceObject, indent: usize, width: usize) {
+        let mut flat = String::new();
+        self.flat(&mut flat, value);
+
+        let AliceObject::Array(array) = value else {
+            text.push_str(&flat);
+            return;
+        };
+        if indent + flat.chars().count() <= width || array.borrow().is_empty() {
+            text.push_str(&flat);
+            return;
+        }
+
+        self.array(text, array, |writer, text, values| {
+            text.push_str("[\n");
+            for (i, value) in values.iter().enumerate() {
+                text.extend(std::iter::repeat_n(' ', indent + INDENT));
+                writer.pretty(text, value, indent + INDENT, width);
+                text.push_str(if i + 1 < values.len() { ",\n" } else { "\n" });
+            }
+            text.extend(std::iter::repeat_n(' ', indent));
+            text.push(']');
+        });
+    }
+
+    /// Writes `array` with `write`, or `[...]` if it is already being written
+    /// or nested too deeply.
+    fn array(&mut self, text: &mut String, array: &Rc<Array>, write: impl FnOnce(&mut Writer, &mut String, &[AliceObject])) {
+        let ptr = Rc::as_ptr(array);
+        if self.open.len() == MAX_DEPTH || self.open.contains(&ptr) {
+            text.push_str("[...]");
+            return;
+        }
+
+        self.open.push(ptr);
+        write(self, text, &array.borrow());
+        self.open.pop();
+    }
+}
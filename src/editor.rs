@@ -0,0 +1,400 @@
+use std::{fs::OpenOptions, io::{BufRead, IsTerminal, Read, Write}, path::PathBuf, process::{Command, Stdio}};
+
+/// Most history entries kept in memory.
+const MAX_HISTORY: usize = 1000;
+
+/// What a call to `Editor::read_line` produced.
+pub enum Input {
+    /// A line of input, including its trailing newline.
+    Line(Vec<u8>),
+    /// The user pressed Ctrl-C.
+    Interrupted,
+    Eof
+}
+
+/// Reads lines for the REPL.
+///
+/// On a terminal, lines are edited in raw mode: the cursor moves with the
+/// arrow keys and the usual Emacs bindings, Up/Down walk the history kept
+/// in `~/.alice_history`, Ctrl-R searches it and Tab completes the word
+/// before the cursor. Otherwise (say, input piped in) lines are read as is.
+pub struct Editor {
+    history: Vec<String>,
+    path: Option<PathBuf>,
+    terminal: bool
+}
+
+impl Editor {
+    pub fn new() -> Editor {
+        let path = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".alice_history"));
+        let terminal = std::io::stdin().is_terminal() && std::io::stdout().is_terminal();
+
+        let mut history: Vec<String> = match (&path, terminal) {
+            (Some(path), true) => std::fs::read_to_string(path).unwrap_or_default().lines().map(str::to_owned).collect(),
+            _ => Vec::new()
+        };
+        history.drain(..history.len().saturating_sub(MAX_HISTORY));
+
+        Editor { history, path, terminal }
+    }
+
+    /// Reads a line, offering `completions` (filtered by the word being
+    /// completed) on Tab.
+    pub fn read_line(&mut self, prompt: &str, completions: impl Fn() -> Vec<String>) -> std::io::Result<Input> {
+        let mut stdout = std::io::stdout();
+        if !self.terminal {
+            write!(stdout, "{prompt}")?;
+            stdout.flush()?;
+
+            let mut line = Vec::new();
+            return match std::io::stdin().lock().read_until(b'\n', &mut line)? {
+                0 => Ok(Input::Eof),
+                _ => Ok(Input::Line(line))
+            };
+        }
+
+        let input = match RawMode::enable() {
+            Some(_raw) => Session::new(prompt, &self.history).run(&completions)?,
+            // No `stty`: fall back to the terminal's own line editing.
+            None => {
+                self.terminal = false;
+                return self.read_line(prompt, completions);
+            }
+        };
+
+        if let Input::Line(line) = &input {
+            self.remember(String::from_utf8_lossy(line).trim_end());
+        }
+        Ok(input)
+    }
+
+    fn remember(&mut self, line: &str) {
+        if line.trim().is_empty() || self.history.last().is_some_and(|last| last == line) {
+            return;
+        }
+
+        if self.history.len() == MAX_HISTORY {
+            self.history.remove(0);
+        }
+        self.history.push(line.to_owned());
+
+        // History is a convenience; failing to save it is not worth an error.
+        if let Some(path) = &self.path {
+            if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+                let _ = writeln!(file, "{line}");
+            }
+        }
+    }
+}
+
+/// Puts the terminal into raw mode for as long as it is alive.
+struct RawMode {
+    saved: String
+}
+
+impl RawMode {
+    fn enable() -> Option<RawMode> {
+        let saved = stty(&["-g"])?;
+        stty(&["-icanon", "-echo", "-isig", "-ixon", "min", "1"])?;
+        Some(RawMode { saved: saved.trim().to_owned() })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        stty(&[&self.saved]);
+    }
+}
+
+/// Runs `stty` on the terminal attached to stdin, returning its output.
+fn stty(args: &[&str]) -> Option<String> {
+    let output = Command::new("stty").args(args).stdin(Stdio::inherit()).stderr(Stdio::null()).output().ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+enum Key {
+    Char(char),
+    Ctrl(u8),
+    Enter,
+    Backspace,
+    Delete,
+    Tab,
+    Escape,
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End
+}
+
+/// The state of one line being edited.
+struct Session<'a> {
+    prompt: &'a str,
+    history: &'a [String],
+    line: Vec<char>,
+    cursor: usize,
+    /// Position in `history` shown, `history.len()` for the new line.
+    entry: usize,
+    /// The new line, kept while browsing the history.
+    draft: Vec<char>,
+    stdin: std::io::StdinLock<'static>,
+    stdout: std::io::Stdout
+}
+
+impl<'a> Session<'a> {
+    fn new(prompt: &'a str, history: &'a [String]) -> Session<'a> {
+        Session {
+            prompt,
+            history,
+            line: Vec::new(),
+            cursor: 0,
+            entry: history.len(),
+            draft: Vec::new(),
+            stdin: std::io::stdin().lock(),
+            stdout: std::io::stdout()
+        }
+    }
+
+    fn run(mut self, completions: &impl Fn() -> Vec<String>) -> std::io::Result<Input> {
+        self.refresh()?;
+
+        let mut pending = None;
+        loop {
+            let key = match pending.take() {
+                Some(key) => key,
+                None => match self.key()? {
+                    Some(key) => key,
+                    None => return Ok(Input::Eof)
+                }
+            };
+
+            match key {
+                Key::Enter => {
+                    writeln!(self.stdout)?;
+                    let mut line: String = self.line.iter().collect();
+                    line.push('\n');
+                    return Ok(Input::Line(line.into_bytes()));
+                }
+                Key::Ctrl(b'c') => {
+                    writeln!(self.stdout, "^C")?;
+                    return Ok(Input::Interrupted);
+                }
+                Key::Ctrl(b'd') if self.line.is_empty() => return Ok(Input::Eof),
+                Key::Ctrl(b'd') | Key::Delete => {
+                    if self.cursor < self.line.len() {
+                        self.line.remove(self.cursor);
+                    }
+                }
+                Key::Char(c) => {
+                    self.line.insert(self.cursor, c);
+                    self.cursor += 1;
+                }
+                Key::Backspace | Key::Ctrl(b'h') => {
+                    if self.cursor > 0 {
+                        self.cursor -= 1;
+                        self.line.remove(self.cursor);
+                    }
+                }
+                Key::Left | Key::Ctrl(b'b') => self.cursor = self.cursor.saturating_sub(1),
+                Key::Right | Key::Ctrl(b'f') => self.cursor = (self.cursor + 1).min(self.line.len()),
+                Key::Home | Key::Ctrl(b'a') => self.cursor = 0,
+                Key::End | Key::Ctrl(b'e') => self.cursor = self.line.len(),
+                Key::Ctrl(b'k') => self.line.truncate(self.cursor),
+                Key::Ctrl(b'u') => {
+                    self.line.drain(..self.cursor);
+                    self.cursor = 0;
+                }
+                Key::Ctrl(b'w') => {
+                    let end = self.word_start(char::is_whitespace);
+                    let start = self.line[..end].iter().rposition(|c| c.is_whitespace()).map_or(0, |i| i + 1);
+                    self.line.drain(start..self.cursor);
+                    self.cursor = start;
+                }
+                Key::Ctrl(b'l') => write!(self.stdout, "\x1b[H\x1b[2J")?,
+                Key::Up | Key::Ctrl(b'p') => self.browse(self.entry.checked_sub(1)),
+                Key::Down | Key::Ctrl(b'n') => self.browse(Some(self.entry + 1)),
+                Key::Ctrl(b'r') => pending = self.search()?,
+                Key::Tab => self.complete(completions())?,
+                Key::Ctrl(_) | Key::Escape => continue
+            }
+            self.refresh()?;
+        }
+    }
+
+    /// Decodes the next key press, `None` at the end of input.
+    fn key(&mut self) -> std::io::Result<Option<Key>> {
+        let Some(byte) = self.byte()? else {
+            return Ok(None);
+        };
+
+        let key = match byte {
+            b'\r' | b'\n' => Key::Enter,
+            b'\t' => Key::Tab,
+            0x7f => Key::Backspace,
+            0x1b => self.escape()?,
+            0..=0x1f => Key::Ctrl(byte + b'a' - 1),
+            0x20..=0x7e => Key::Char(byte as char),
+            _ => {
+                // The lead byte of a UTF-8 sequence.
+                let len = match byte {
+                    0xc0..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    _ => 4
+                };
+                let mut bytes = vec![byte];
+                for _ in 1..len {
+                    bytes.extend(self.byte()?);
+                }
+                match std::str::from_utf8(&bytes).ok().and_then(|str| str.chars().next()) {
+                    Some(c) => Key::Char(c),
+                    None => Key::Escape
+                }
+            }
+        };
+        Ok(Some(key))
+    }
+
+    /// Decodes the rest of an escape sequence such as `ESC [ A`.
+    fn escape(&mut self) -> std::io::Result<Key> {
+        let Some(b'[' | b'O') = self.byte()? else {
+            return Ok(Key::Escape);
+        };
+
+        let mut parameter = Vec::new();
+        let last = loop {
+            match self.byte()? {
+                Some(byte @ b'0'..=b'9' | byte @ b';') => parameter.push(byte),
+                Some(byte) => break byte,
+                None => return Ok(Key::Escape)
+            }
+        };
+
+        let key = match (last, parameter.as_slice()) {
+            (b'A', _) => Key::Up,
+            (b'B', _) => Key::Down,
+            (b'C', _) => Key::Right,
+            (b'D', _) => Key::Left,
+            (b'H', _) | (b'~', b"1" | b"7") => Key::Home,
+            (b'F', _) | (b'~', b"4" | b"8") => Key::End,
+            (b'~', b"3") => Key::Delete,
+            _ => Key::Escape
+        };
+        Ok(key)
+    }
+
+    fn byte(&mut self) -> std::io::Result<Option<u8>> {
+        let mut byte = [0];
+        match self.stdin.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0]))
+        }
+    }
+
+    /// Shows history entry `entry`, or the draft past the newest one.
+    fn browse(&mut self, entry: Option<usize>) {
+        let Some(entry) = entry.filter(|&entry| entry <= self.history.len()) else {
+            return;
+        };
+
+        if self.entry == self.history.len() {
+            self.draft = std::mem::take(&mut self.line);
+        }
+        self.entry = entry;
+        self.line = match self.history.get(entry) {
+            Some(line) => line.chars().collect(),
+            None => std::mem::take(&mut self.draft)
+        };
+        self.cursor = self.line.len();
+    }
+
+    /// Incremental reverse search through the history. Any key other than
+    /// typing, Backspace or Ctrl-R accepts the line found and is returned to
+    /// be handled as usual; Ctrl-C or Ctrl-G cancels the search.
+    fn search(&mut self) -> std::io::Result<Option<Key>> {
+        let mut query = String::new();
+        let mut found = self.history.len();
+
+        loop {
+            let shown = self.history.get(found).map_or("", String::as_str);
+            write!(self.stdout, "\r(reverse-i-search)`{query}': {shown}\x1b[K")?;
+            self.stdout.flush()?;
+
+            let from = match self.key()? {
+                Some(Key::Char(c)) => {
+                    query.push(c);
+                    found + 1
+                }
+                Some(Key::Backspace) => {
+                    query.pop();
+                    self.history.len()
+                }
+                Some(Key::Ctrl(b'r')) => found,
+                Some(Key::Ctrl(b'c' | b'g')) => return Ok(None),
+                key => {
+                    if let Some(line) = self.history.get(found) {
+                        if self.entry == self.history.len() {
+                            self.draft = std::mem::take(&mut self.line);
+                        }
+                        self.line = line.chars().collect();
+                        self.cursor = self.line.len();
+                        self.entry = found;
+                    }
+                    return Ok(key);
+                }
+            };
+
+            found = self.history[..from.min(self.history.len())].iter()
+                .rposition(|line| line.contains(&query))
+                .unwrap_or(self.history.len());
+        }
+    }
+
+    /// Completes the identifier before the cursor from `candidates`.
+    fn complete(&mut self, candidates: Vec<String>) -> std::io::Result<()> {
+        let start = self.word_start(|c| c.is_alphanumeric() || c == '_');
+        let prefix: String = self.line[start..self.cursor].iter().collect();
+        if prefix.is_empty() {
+            return Ok(());
+        }
+
+        let mut matches: Vec<String> = candidates.into_iter().filter(|candidate| candidate.starts_with(&prefix)).collect();
+        matches.sort();
+        matches.dedup();
+
+        let Some(first) = matches.first() else {
+            return Ok(());
+        };
+        let common = matches.iter().fold(first.as_str(), |common, candidate| {
+            let len = common.char_indices().zip(candidate.chars())
+                .find(|((_, a), b)| a != b)
+                .map_or(common.len().min(candidate.len()), |((i, _), _)| i);
+            &common[..len]
+        });
+
+        if common.len() > prefix.len() {
+            let rest: Vec<char> = common[prefix.len()..].chars().collect();
+            self.cursor += rest.len();
+            self.line.splice(self.cursor - rest.len()..self.cursor - rest.len(), rest);
+        } else if matches.len() > 1 {
+            writeln!(self.stdout)?;
+            writeln!(self.stdout, "{}", matches.join("  "))?;
+        }
+        Ok(())
+    }
+
+    /// Start of the run of characters matching `part` that ends at the cursor.
+    fn word_start(&self, part: impl Fn(char) -> bool) -> usize {
+        self.line[..self.cursor].iter().rposition(|&c| !part(c)).map_or(0, |i| i + 1)
+    }
+
+    fn refresh(&mut self) -> std::io::Result<()> {
+        let line: String = self.line.iter().collect();
+        write!(self.stdout, "\r{}{line}\x1b[K", self.prompt)?;
+        let back = self.line.len() - self.cursor;
+        if back > 0 {
+            write!(self.stdout, "\x1b[{back}D")?;
+        }
+        self.stdout.flush()
+    }
+}
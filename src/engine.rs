@@ -0,0 +1,174 @@
+use std::rc::Rc;
+
+use crate::{ast::AliceObject, environment::Globals, error::AliceError, heap::HeapStats, host::Host, native::{self, Arity, NativeModule}, ops, optimizer::Optimizer, program::Program, resolver::Resolver, scanner::Scanner, symbol::Symbol, value::{Args, Value}};
+
+/// How many calls deep a program may go before it fails with "Stack
+/// overflow.", unless the engine is told otherwise. Tail calls replace the
+/// returning call and don't count. The tree engine takes some 15 KB of
+/// native stack a call in a debug build, so this fits a thread's default
+/// 2 MB stack.
+const MAX_CALL_DEPTH: usize = 100;
+
+/// The state the `Interpreter` and the `Vm` keep alike: the globals, the
+/// resolver that remembers the globals earlier programs declared, whether
+/// to optimize programs and how deep they may call.
+pub struct Core {
+    pub(crate) globals: Globals,
+    resolver: Resolver,
+    optimize: bool,
+    pub(crate) max_call_depth: usize
+}
+
+impl Core {
+    /// Globals holding the builtins, which reach outside through `host`.
+    pub(crate) fn new(host: Rc<dyn Host>) -> Core {
+        let mut globals = Globals::default();
+        native::define_builtins(&mut globals, host);
+        Core { globals, resolver: Resolver::new(), optimize: false, max_call_depth: MAX_CALL_DEPTH }
+    }
+}
+
+pub(crate) mod sealed {
+    use crate::{ast::{AliceObject, Stmt}, error::AliceError, heap::Heap};
+
+    use super::Core;
+
+    /// What an engine provides for `Engine`'s methods, kept out of reach
+    /// so that only this crate's engines implement it.
+    pub trait Sealed {
+        fn core(&self) -> &Core;
+
+        fn core_mut(&mut self) -> &mut Core;
+
+        fn heap(&self) -> &Heap;
+
+        fn heap_mut(&mut self) -> &mut Heap;
+
+        /// Runs resolved `statements`, evaluating a trailing bare expression
+        /// to its value and anything else to `nil`.
+        fn run_statements(&mut self, statements: &[Stmt]) -> Result<AliceObject, AliceError>;
+    }
+}
+
+/// What embedders do with either engine, the `Interpreter` or the `Vm`.
+pub trait Engine: sealed::Sealed {
+    /// Makes `function` callable from scripts as `name`. `arity` is the
+    /// number of arguments it takes, or an `Arity` such as `AtLeast(0)` for
+    /// a variadic function. The function gets the arguments as `Args`,
+    /// which copies an argument into a `Value` only when it is read. Errors
+    /// made with `AliceError::native` are reported at the call.
+    fn register_native(&mut self, name: &str, arity: impl Into<Arity>, function: impl Fn(Args) -> Result<Value, AliceError> + 'static) {
+        native::register(&mut self.core_mut().globals, name, arity.into(), function);
+    }
+
+    /// Defines `module` as a global, so scripts can call `module.function()`.
+    fn register_module(&mut self, module: NativeModule) {
+        native::adopt_module(self.heap_mut(), &module);
+        native::register_module(&mut self.core_mut().globals, module);
+    }
+
+    /// Sets the command-line arguments `args()` returns to the script.
+    fn set_args(&mut self, args: Vec<String>) {
+        native::define_args(&mut self.core_mut().globals, args);
+    }
+
+    /// Whether to run the AST optimizer on programs before running them.
+    /// Off by default.
+    fn set_optimize(&mut self, optimize: bool) {
+        self.core_mut().optimize = optimize;
+    }
+
+    /// How many calls deep programs may go before they fail with "Stack
+    /// overflow.". 100 by default. The `Interpreter` recurses on the native
+    /// stack for each call, so running it deeper takes a thread with a
+    /// bigger stack; the `Vm` doesn't.
+    fn set_max_call_depth(&mut self, depth: usize) {
+        self.core_mut().max_call_depth = depth;
+    }
+
+    /// Resolves and runs `program`. Globals persist from one program to the
+    /// next, as in an interactive session.
+    ///
+    /// A call to `exit(status)` ends the program with an error whose kind
+    /// is `ErrorKind::Exit(status)`; it is not a failure of the program.
+    fn run(&mut self, program: Program) -> Result<(), AliceError> {
+        run_program(self, program).map(|_| ())
+    }
+
+    /// Runs one input of an interactive session like `run`. If the last
+    /// statement is a bare expression, returns its value for echoing,
+    /// unless that is `nil`.
+    fn run_line(&mut self, program: Program) -> Result<Option<String>, AliceError> {
+        match run_program(self, program)? {
+            AliceObject::Nil => Ok(None),
+            value => Ok(Some(ops::stringify(value)))
+        }
+    }
+
+    /// Scans, parses and runs `source` like `run`, returning the value of
+    /// its last statement if that is a bare expression, and `nil` otherwise.
+    ///
+    /// A call to `exit(status)` ends the program with an error whose kind
+    /// is `ErrorKind::Exit(status)`, as for `run`.
+    fn eval_str(&mut self, source: &str) -> Result<Value, AliceError> {
+        let tokens = match Scanner::new(source).scan_tokens() {
+            Ok(tokens) => tokens,
+            Err(errors) => {
+                let count = errors.len();
+                let first = errors.into_iter().next().expect("a failed scan has at least one error");
+                return Err(match count {
+                    1 => first,
+                    count => first.with_note(format!("found {} syntax errors in all", count))
+                });
+            }
+        };
+
+        let value = run_program(self, Program::parse(source, tokens)?)?;
+        Value::try_from(value)
+    }
+
+    /// The value of the global `name`.
+    fn get_global(&self, name: &str) -> Result<Value, AliceError> {
+        match self.core().globals.lookup(Symbol::intern(name)) {
+            Some(value) => Value::try_from(value),
+            None => Err(AliceError::native(format!("Undefined variable '{}'.", name)))
+        }
+    }
+
+    /// Defines or overwrites the global `name`.
+    fn set_global(&mut self, name: &str, value: impl Into<Value>) {
+        let value = self.heap_mut().value(value.into());
+        self.core_mut().globals.define(Symbol::intern(name), value);
+    }
+
+    /// The names of every global variable, in no particular order.
+    fn global_names(&self) -> impl Iterator<Item = &str> {
+        self.core().globals.names()
+    }
+
+    /// Every global variable as `(name, value)`, sorted by name.
+    fn globals(&self) -> Vec<(&str, String)> {
+        self.core().globals.describe()
+    }
+
+    /// Frees environments and arrays that are only kept alive by reference
+    /// cycles, returning how many were freed. The engine also collects on
+    /// its own once enough has been allocated.
+    fn collect_garbage(&mut self) -> usize {
+        self.heap_mut().collect()
+    }
+
+    fn heap_stats(&self) -> HeapStats {
+        self.heap().stats()
+    }
+}
+
+/// Resolves, optimizes if asked to and runs `program` on `engine`.
+fn run_program(engine: &mut (impl Engine + ?Sized), mut program: Program) -> Result<AliceObject, AliceError> {
+    let core = engine.core_mut();
+    core.resolver.resolve(&mut program.statements)?;
+    if core.optimize {
+        Optimizer::new().optimize(&mut program.statements);
+    }
+    engine.run_statements(&program.statements)
+}
@@ -1,49 +1,109 @@
 use std::{collections::HashMap, rc::Rc, cell::RefCell};
 
-use crate::{ast::AliceObject, error::AliceError, token::Token};
+use crate::{ast::AliceObject, error::AliceError, ops, symbol::Symbol, token::Token};
 
-#[derive(Clone)]
+/// A local scope. Variables live in `values` at the slot the `Resolver`
+/// assigned them, in declaration order.
+#[derive(Default)]
 pub struct Environment {
-    values: HashMap<String, AliceObject>,
+    values: Vec<AliceObject>,
     environment: Option<Rc<RefCell<Environment>>>
 }
 
 impl Environment {
-    pub fn new() -> Environment {
-        Environment { values: HashMap::new(), environment: None }
+    #[inline]
+    pub fn from(environment: Option<Rc<RefCell<Environment>>>) -> Environment {
+        Environment { values: Vec::new(), environment }
     }
 
-    pub fn from(environment: Rc<RefCell<Environment>>) -> Environment {
-        Environment { values: HashMap::new(), environment: Some(environment) }
+    #[inline]
+    pub fn define(&mut self, value: AliceObject) {
+        self.values.push(value);
     }
 
-    pub fn get(&self, name: Token) -> Result<AliceObject, AliceError> {
-        let lexeme = name.clone().lexeme.unwrap();
-        if self.values.contains_key(&lexeme) {
-            Ok(self.values.get(&lexeme).unwrap().clone())
+    pub fn get_at(&self, depth: usize, slot: usize) -> AliceObject {
+        if depth == 0 {
+            self.values[slot].clone()
         } else {
-            if let Some(environment) = &self.environment {
-                environment.borrow().get(name)
-            } else {
-                Err(AliceError::RuntimeError(format!("Undefined variable '{}'.", &lexeme).into(), name.line))
-            }
+            self.enclosing().borrow().get_at(depth - 1, slot)
         }
     }
 
-    pub fn define(&mut self, name: String, value: AliceObject) {
+    pub fn assign_at(&mut self, depth: usize, slot: usize, value: AliceObject) {
+        if depth == 0 {
+            self.values[slot] = value;
+        } else {
+            self.enclosing().borrow_mut().assign_at(depth - 1, slot, value)
+        }
+    }
+
+    #[inline]
+    pub fn values(&self) -> &[AliceObject] {
+        &self.values
+    }
+
+    /// The scope this one is nested in, `None` for a top-level scope.
+    #[inline]
+    pub fn parent(&self) -> Option<Rc<RefCell<Environment>>> {
+        self.environment.clone()
+    }
+
+    #[inline]
+    fn enclosing(&self) -> &Rc<RefCell<Environment>> {
+        self.environment.as_ref().expect("resolver produced a binding deeper than the scope chain")
+    }
+}
+
+/// Top-level variables, which the resolver leaves unbound and which are
+/// looked up by name.
+#[derive(Default)]
+pub struct Globals {
+    values: HashMap<Symbol, AliceObject>
+}
+
+impl Globals {
+    pub fn get(&self, name: &Token) -> Result<AliceObject, AliceError> {
+        let lexeme = name.symbol();
+        match self.values.get(&lexeme) {
+            Some(value) => Ok(value.clone()),
+            None => Err(AliceError::runtime(format!("Undefined variable '{}'.", lexeme), name))
+        }
+    }
+
+    #[inline]
+    pub fn lookup(&self, name: Symbol) -> Option<AliceObject> {
+        self.values.get(&name).cloned()
+    }
+
+    /// The names of every global, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(|name| name.as_str())
+    }
+
+    /// Every global apart from native functions and modules as
+    /// `(name, value)`, sorted by name.
+    pub fn describe(&self) -> Vec<(&str, String)> {
+        let mut globals: Vec<_> = self.values.iter()
+            .filter(|(_, value)| !matches!(value, AliceObject::Native(_) | AliceObject::Module(_)))
+            .map(|(name, value)| (name.as_str(), ops::stringify(value.clone())))
+            .collect();
+        globals.sort();
+        globals
+    }
+
+    #[inline]
+    pub fn define(&mut self, name: Symbol, value: AliceObject) {
         self.values.insert(name, value);
     }
 
-    pub fn assign(&mut self, name: Token, value: AliceObject) -> Result<AliceObject, AliceError> {
-        let lexeme = name.clone().lexeme.unwrap();
-        if self.values.contains_key(&lexeme) {
-            Ok(self.values.insert(lexeme, value).unwrap())
-        } else {
-            if let Some(environment) = &mut self.environment {
-                environment.borrow_mut().assign(name, value)
-            } else {
-                Err(AliceError::RuntimeError(format!("Undefined variable '{}'.", lexeme).into(), name.line))   
+    pub fn assign(&mut self, name: &Token, value: AliceObject) -> Result<(), AliceError> {
+        let lexeme = name.symbol();
+        match self.values.get_mut(&lexeme) {
+            Some(slot) => {
+                *slot = value;
+                Ok(())
             }
+            None => Err(AliceError::runtime(format!("Undefined variable '{}'.", lexeme), name))
         }
-    }   
+    }
 }
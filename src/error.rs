@@ -1,18 +1,170 @@
-use std::fmt::Display;
+use std::fmt::{Display, Write};
+
+use crate::token::{Token, Span};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    Syntax,
+    Parse,
+    Runtime,
+    /// Not an error: the program called `exit(status)`. Unwinding with it
+    /// runs no further code.
+    Exit(i32)
+}
 
 #[derive(Debug)]
-pub enum AliceError {
-    SyntaxError(Box<str>, u32),
-    ParseError(Box<str>, u32),
-    RuntimeError(Box<str>, u32)
+pub struct AliceError {
+    pub kind: ErrorKind,
+    pub message: Box<str>,
+    pub file: Option<Box<str>>,
+    pub line: u32,
+    pub column: u32,
+    pub span: Option<Span>,
+    pub notes: Vec<Box<str>>,
+    pub suggestions: Vec<Box<str>>
 }
 
-impl Display for AliceError {
+impl AliceError {
+    pub fn new(kind: ErrorKind, message: impl Into<Box<str>>, line: u32) -> AliceError {
+        AliceError {
+            kind,
+            message: message.into(),
+            file: None,
+            line,
+            column: 0,
+            span: None,
+            notes: Vec::new(),
+            suggestions: Vec::new()
+        }
+    }
+
+    #[inline]
+    pub fn syntax(message: impl Into<Box<str>>, line: u32, column: u32, span: Span) -> AliceError {
+        let mut error = AliceError::new(ErrorKind::Syntax, message, line);
+        error.column = column;
+        error.span = Some(span);
+        error
+    }
+
+    #[inline]
+    pub fn parse(message: impl Into<Box<str>>, token: &Token) -> AliceError {
+        AliceError::new(ErrorKind::Parse, message, token.line).at(token)
+    }
+
+    #[inline]
+    pub fn runtime(message: impl Into<Box<str>>, token: &Token) -> AliceError {
+        AliceError::new(ErrorKind::Runtime, message, token.line).at(token)
+    }
+
+    /// A runtime error raised by a native function. It is reported at the
+    /// call that failed.
+    #[inline]
+    pub fn native(message: impl Into<Box<str>>) -> AliceError {
+        AliceError::new(ErrorKind::Runtime, message, 0)
+    }
+
+    #[inline]
+    pub fn exit(status: i32) -> AliceError {
+        AliceError::new(ErrorKind::Exit(status), format!("exit({status})"), 0)
+    }
+
+    #[inline]
+    pub fn at(mut self, token: &Token) -> AliceError {
+        self.line = token.line;
+        self.column = token.column;
+        self.span = Some(token.span);
+        self
+    }
+
+    #[inline]
+    pub fn with_file(mut self, file: &str) -> AliceError {
+        self.file = Some(file.into());
+        self
+    }
+
+    #[inline]
+    pub fn with_note(mut self, note: impl Into<Box<str>>) -> AliceError {
+        self.notes.push(note.into());
+        self
+    }
+
+    #[inline]
+    pub fn with_suggestion(mut self, suggestion: impl Into<Box<str>>) -> AliceError {
+        self.suggestions.push(suggestion.into());
+        self
+    }
+
+    /// Serializes the error as a single-line JSON object, for editors and CI
+    /// tooling that read `--error-format=json`.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{");
+
+        write!(json, "\"kind\":\"{}\"", self.kind).unwrap();
+        json.push_str(",\"message\":");
+        push_json_str(&mut json, &self.message);
+        json.push_str(",\"file\":");
+        match &self.file {
+            Some(file) => push_json_str(&mut json, file),
+            None => json.push_str("null")
+        }
+        write!(json, ",\"line\":{},\"column\":{}", self.line, self.column).unwrap();
+        json.push_str(",\"span\":");
+        match self.span {
+            Some(span) => write!(json, "{{\"start\":{},\"end\":{}}}", span.start, span.end).unwrap(),
+            None => json.push_str("null")
+        }
+        json.push_str(",\"notes\":");
+        push_json_array(&mut json, &self.notes);
+        json.push_str(",\"suggestions\":");
+        push_json_array(&mut json, &self.suggestions);
+        json.push('}');
+
+        json
+    }
+}
+
+fn push_json_array(json: &mut String, items: &[Box<str>]) {
+    json.push('[');
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+        push_json_str(json, item);
+    }
+    json.push(']');
+}
+
+fn push_json_str(json: &mut String, str: &str) {
+    json.push('"');
+    for c in str.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c)
+        }
+    }
+    json.push('"');
+}
+
+impl Display for ErrorKind {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            AliceError::SyntaxError(e, line) => write!(f, "line[{}] SyntaxError: {}", line, e),
-            AliceError::ParseError(e, line) => write!(f, "line[{}] ParseError: {}", line, e),
-            AliceError::RuntimeError(e, line) => write!(f, "line[{}] RuntimeError: {}", line, e)
+            ErrorKind::Syntax => write!(f, "SyntaxError"),
+            ErrorKind::Parse => write!(f, "ParseError"),
+            ErrorKind::Runtime => write!(f, "RuntimeError"),
+            ErrorKind::Exit(_) => write!(f, "Exit")
         }
     }
-}
\ No newline at end of file
+}
+
+impl Display for AliceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line[{}] {}: {}", self.line, self.kind, self.message)
+    }
+}
+
+impl std::error::Error for AliceError {}
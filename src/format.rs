@@ -0,0 +1,244 @@
+use std::fmt::Write;
+
+use crate::{ast::AliceObject, display, ops};
+
+// The formatting engine behind `format`, modeled on Rust's format strings:
+//
+//     {}  {0}  {name}  {:>8.2}  {name:_^10}  {:+}  {:#x}  {:08b}  {:?}  {{ }}
+//
+// `{}` takes the next positional argument and `{n}` the positional argument
+// at index n. A named `{name}` takes the keyword argument `name`, as in
+// `format("{greeting}, {who}!", greeting: "hello", who: "alice")`.
+
+/// The result of formatting: the text, and which arguments were used.
+pub struct Formatted {
+    pub text: String,
+    pub used: Vec<bool>
+}
+
+/// Formats `arguments` into `template`. The last `names.len()` arguments
+/// are the keyword arguments of those names. Errors are messages to be
+/// reported as a runtime error.
+pub fn format(template: &str, arguments: &[AliceObject], names: &[&str]) -> Result<Formatted, String> {
+    let mut text = String::with_capacity(template.len());
+    let mut used = vec![false; arguments.len()];
+    let mut next = 0;
+
+    let (positionals, keywords) = arguments.split_at(arguments.len() - names.len());
+
+    let mut chars = template.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            '{' if chars.next_if(|&(_, c)| c == '{').is_some() => text.push('{'),
+            '}' if chars.next_if(|&(_, c)| c == '}').is_some() => text.push('}'),
+            '}' => return Err(format!("Invalid format string: unmatched '}}' at {}.", start)),
+            '{' => {
+                let Some(end) = template[start..].find('}').map(|end| start + end) else {
+                    return Err(format!("Invalid format string: unmatched '{{' at {}.", start));
+                };
+                let (argument, spec) = match template[start + 1..end].split_once(':') {
+                    Some((argument, spec)) => (argument, spec),
+                    None => (&template[start + 1..end], "")
+                };
+
+                let (index, value) = match argument {
+                    "" => {
+                        next += 1;
+                        positional(positionals, next - 1)?
+                    }
+                    argument if argument.bytes().all(|b| b.is_ascii_digit()) => {
+                        let index = argument.parse().map_err(|_| format!("Invalid argument index '{}'.", argument))?;
+                        positional(positionals, index)?
+                    }
+                    name => match names.iter().position(|&keyword| keyword == name) {
+                        Some(index) => (positionals.len() + index, keywords[index].clone()),
+                        None => return Err(format!("No argument named '{}'.", name))
+                    }
+                };
+
+                used[index] = true;
+                write(&mut text, &value, &Spec::parse(spec)?)?;
+                while chars.next_if(|&(i, _)| i <= end).is_some() {}
+            }
+            c => text.push(c)
+        }
+    }
+
+    Ok(Formatted { text, used })
+}
+
+fn positional(arguments: &[AliceObject], index: usize) -> Result<(usize, AliceObject), String> {
+    match arguments.get(index) {
+        Some(value) => Ok((index, value.clone())),
+        None => Err(format!("Format string refers to argument {} but only {} were given.", index, arguments.len()))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Center,
+    Right
+}
+
+/// A parsed `[[fill]align][+][#][0][width][.precision][type]`.
+struct Spec {
+    fill: char,
+    align: Option<Align>,
+    plus: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    r#type: Option<char>
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Result<Spec, String> {
+        let invalid = || format!("Invalid format spec '{}'.", spec);
+        let align = |c| match c {
+            '<' => Some(Align::Left),
+            '^' => Some(Align::Center),
+            '>' => Some(Align::Right),
+            _ => None
+        };
+
+        let mut parsed = Spec { fill: ' ', align: None, plus: false, alternate: false, zero: false, width: 0, precision: None, r#type: None };
+        let chars: Vec<char> = spec.chars().collect();
+        let mut i = 0;
+
+        if let Some(a) = chars.get(1).and_then(|&c| align(c)) {
+            parsed.fill = chars[0];
+            parsed.align = Some(a);
+            i = 2;
+        } else if let Some(a) = chars.first().and_then(|&c| align(c)) {
+            parsed.align = Some(a);
+            i = 1;
+        }
+
+        if chars.get(i) == Some(&'+') {
+            parsed.plus = true;
+            i += 1;
+        }
+        if chars.get(i) == Some(&'#') {
+            parsed.alternate = true;
+            i += 1;
+        }
+        if chars.get(i) == Some(&'0') {
+            parsed.zero = true;
+            i += 1;
+        }
+
+        let digits = |i: &mut usize| {
+            let start = *i;
+            while chars.get(*i).is_some_and(char::is_ascii_digit) {
+                *i += 1;
+            }
+            chars[start..*i].iter().collect::<String>()
+        };
+
+        let width = digits(&mut i);
+        if !width.is_empty() {
+            parsed.width = width.parse().map_err(|_| invalid())?;
+        }
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            parsed.precision = Some(digits(&mut i).parse().map_err(|_| invalid())?);
+        }
+
+        match &chars[i..] {
+            [] => (),
+            [c @ ('?' | 'x' | 'X' | 'b' | 'o' | 'e' | 'E')] => parsed.r#type = Some(*c),
+            _ => return Err(invalid())
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// Writes `value` to `text` as `spec` says.
+fn write(text: &mut String, value: &AliceObject, spec: &Spec) -> Result<(), String> {
+    let numeric = matches!(value, AliceObject::I64(_) | AliceObject::F64(_));
+
+    let (sign, prefix, mut body) = match (spec.r#type, value) {
+        (Some('?'), value) => ("", "", display::debug(value)),
+        (Some(radix @ ('x' | 'X' | 'b' | 'o')), AliceObject::I64(num)) => {
+            let body = match radix {
+                'x' => format!("{:x}", num),
+                'X' => format!("{:X}", num),
+                'b' => format!("{:b}", num),
+                _ => format!("{:o}", num)
+            };
+            let prefix = match (spec.alternate, radix) {
+                (false, _) => "",
+                (true, 'x' | 'X') => "0x",
+                (true, 'b') => "0b",
+                (true, _) => "0o"
+            };
+            ("", prefix, body)
+        }
+        (Some(radix @ ('x' | 'X' | 'b' | 'o')), value) => {
+            return Err(format!("'{}' formats i64 values, not {}.", radix, value.type_name()));
+        }
+        (Some(exp @ ('e' | 'E')), &AliceObject::I64(num)) => split_sign(exponent(exp, num as f64, spec.precision)),
+        (Some(exp @ ('e' | 'E')), &AliceObject::F64(num)) => split_sign(exponent(exp, num, spec.precision)),
+        (Some(exp), value) => {
+            return Err(format!("'{}' formats numbers, not {}.", exp, value.type_name()));
+        }
+        (None, AliceObject::F64(num)) => match spec.precision {
+            Some(precision) => split_sign(format!("{:.*}", precision, num)),
+            None => split_sign(ops::stringify(value.clone()))
+        },
+        (None, AliceObject::I64(_)) => split_sign(ops::stringify(value.clone())),
+        (None, value) => {
+            let text = ops::stringify(value.clone());
+            match spec.precision {
+                Some(precision) => ("", "", text.chars().take(precision).collect()),
+                None => ("", "", text)
+            }
+        }
+    };
+
+    let sign = match sign {
+        "" if spec.plus && numeric => "+",
+        sign => sign
+    };
+
+    let len = sign.chars().count() + prefix.len() + body.chars().count();
+    let padding = spec.width.saturating_sub(len);
+
+    if spec.zero && numeric {
+        body.insert_str(0, &"0".repeat(padding));
+        write!(text, "{sign}{prefix}{body}").expect("writing to a String does not fail");
+        return Ok(());
+    }
+
+    let align = spec.align.unwrap_or(if numeric { Align::Right } else { Align::Left });
+    let (before, after) = match align {
+        Align::Left => (0, padding),
+        Align::Center => (padding / 2, padding - padding / 2),
+        Align::Right => (padding, 0)
+    };
+
+    text.extend(std::iter::repeat_n(spec.fill, before));
+    write!(text, "{sign}{prefix}{body}").expect("writing to a String does not fail");
+    text.extend(std::iter::repeat_n(spec.fill, after));
+    Ok(())
+}
+
+fn exponent(exp: char, num: f64, precision: Option<usize>) -> String {
+    match (precision, exp) {
+        (Some(precision), 'e') => format!("{:.*e}", precision, num),
+        (Some(precision), _) => format!("{:.*E}", precision, num),
+        (None, 'e') => format!("{:e}", num),
+        (None, _) => format!("{:E}", num)
+    }
+}
+
+/// Splits a leading minus sign off a formatted number.
+fn split_sign(text: String) -> (&'static str, &'static str, String) {
+    match text.strip_prefix('-') {
+        Some(rest) => ("-", "", rest.to_owned()),
+        None => ("", "", text)
+    }
+}
@@ -0,0 +1,35 @@
+use std::{rc::Rc, cell::RefCell, fmt::Debug};
+
+use crate::{environment::Environment, ast::Stmt, chunk::Chunk, symbol::Symbol, token::Token};
+
+pub struct AliceFunction {
+    pub name: Token,
+    pub params: Vec<Token>,
+    pub body: Body,
+    pub closure: Option<Rc<RefCell<Environment>>>
+}
+
+/// Functions declared by the tree-walking `Interpreter` keep their AST;
+/// functions created by the `Vm` point at their compiled chunk.
+pub enum Body {
+    Ast(Rc<Vec<Stmt>>),
+    Bytecode(Rc<Chunk>)
+}
+
+impl AliceFunction {
+    #[inline]
+    pub fn name(&self) -> &str {
+        self.name.lexeme.as_ref().map_or("", Symbol::as_str)
+    }
+
+    #[inline]
+    pub fn arity(&self) -> usize {
+        self.params.len()
+    }
+}
+
+impl Debug for AliceFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<fn {}>", self.name())
+    }
+}
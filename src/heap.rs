@@ -0,0 +1,315 @@
+use std::{rc::{Rc, Weak}, cell::RefCell, collections::{hash_map::Entry, HashMap}, hash::{BuildHasherDefault, Hasher}};
+
+use crate::{ast::{AliceObject, Array}, environment::Environment, function::AliceFunction, native::NativeModule, value::Value};
+
+/// Allocation counts for an engine's heap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct HeapStats {
+    pub environments: usize,
+    pub arrays: usize,
+    /// The most objects the heap has tracked at once, including ones freed
+    /// since the last collection.
+    pub peak: usize
+}
+
+impl HeapStats {
+    #[inline]
+    pub fn total(&self) -> usize {
+        self.environments + self.arrays
+    }
+}
+
+/// Tracks the environments and arrays an engine allocates so reference
+/// cycles between them can be reclaimed.
+///
+/// Values are still plain `Rc`s and are freed as soon as their last
+/// reference goes away; the heap only keeps weak references. A collection
+/// finds the objects that are only referenced by each other: it counts the
+/// references between the tracked objects (and the functions, modules and
+/// arrays they lead to), and any object with more references than that is
+/// held from outside, by the globals, an engine's stack or an embedder. The
+/// tracked objects that can't be reached from one of those are emptied,
+/// which breaks their cycles and lets `Rc` free them.
+///
+/// As the roots are found rather than given, a collection may run at any
+/// allocation. An object that is borrowed mutably while it runs is taken to
+/// be held from outside.
+pub struct Heap {
+    environments: Vec<Weak<RefCell<Environment>>>,
+    arrays: Vec<Weak<Array>>,
+    allocated: usize,
+    threshold: usize,
+    peak: usize
+}
+
+const INITIAL_THRESHOLD: usize = 1 << 12;
+
+impl Default for Heap {
+    fn default() -> Self {
+        Heap { environments: Vec::new(), arrays: Vec::new(), allocated: 0, threshold: INITIAL_THRESHOLD, peak: 0 }
+    }
+}
+
+impl Heap {
+    pub fn environment(&mut self, environment: Environment) -> Rc<RefCell<Environment>> {
+        let environment = Rc::new(RefCell::new(environment));
+        self.environments.push(Rc::downgrade(&environment));
+        self.allocated();
+        environment
+    }
+
+    pub fn array(&mut self, values: Vec<AliceObject>) -> AliceObject {
+        let array = Rc::new(Array::new(values));
+        self.arrays.push(Rc::downgrade(&array));
+        self.allocated();
+        AliceObject::Array(array)
+    }
+
+    /// Converts `value` into the engine's own, tracking its arrays.
+    pub fn value(&mut self, value: Value) -> AliceObject {
+        let value = AliceObject::from(value);
+        self.adopt(&value);
+        value
+    }
+
+    /// Tracks the arrays in `value`, which were made outside the heap, as
+    /// a module's values are. Nothing tracked may reference them yet.
+    pub fn adopt(&mut self, value: &AliceObject) {
+        let AliceObject::Array(array) = value else { return };
+        let mut pending = vec![array.clone()];
+        while let Some(array) = pending.pop() {
+            self.arrays.push(Rc::downgrade(&array));
+            self.allocated();
+            pending.extend(array.borrow().iter().filter_map(|value| match value {
+                AliceObject::Array(array) => Some(array.clone()),
+                _ => None
+            }));
+        }
+    }
+
+    /// The value of a literal expression. Array constants (produced by the
+    /// optimizer) are copied so each evaluation yields a distinct array.
+    pub fn literal(&mut self, value: &AliceObject) -> AliceObject {
+        match value {
+            AliceObject::Array(array) => {
+                let values = array.borrow().iter().map(|value| self.literal(value)).collect();
+                self.array(values)
+            }
+            value => value.clone()
+        }
+    }
+
+    /// Collects once enough has been allocated since the last collection to
+    /// make another one worthwhile.
+    fn allocated(&mut self) {
+        self.allocated += 1;
+        self.peak = self.peak.max(self.environments.len() + self.arrays.len());
+        if self.allocated >= self.threshold {
+            self.collect();
+        }
+    }
+
+    /// Reclaims every tracked object that is only kept alive by reference
+    /// cycles, returning how many were freed.
+    pub fn collect(&mut self) -> usize {
+        let mut graph = Graph::default();
+        self.environments.iter().filter_map(Weak::upgrade).for_each(|environment| {
+            graph.add(Node::Environment(environment));
+        });
+        self.arrays.iter().filter_map(Weak::upgrade).for_each(|array| {
+            graph.add(Node::Array(array));
+        });
+        graph.link();
+        let live = graph.mark();
+
+        // Take the contents out first and drop them once no `RefCell` is
+        // borrowed, since dropping may free further tracked objects.
+        let mut environments = Vec::new();
+        let mut arrays = Vec::new();
+        for (node, live) in graph.nodes.iter().zip(live) {
+            match node {
+                Node::Environment(environment) if !live => if let Ok(mut environment) = environment.try_borrow_mut() {
+                    environments.push(std::mem::take(&mut *environment));
+                },
+                Node::Array(array) if !live => if let Ok(mut array) = array.try_borrow_mut() {
+                    arrays.push(std::mem::take(&mut *array));
+                },
+                _ => ()
+            }
+        }
+        drop(graph);
+
+        let freed = environments.len() + arrays.len();
+        drop(environments);
+        drop(arrays);
+
+        self.environments.retain(|environment| environment.strong_count() > 0);
+        self.arrays.retain(|array| array.strong_count() > 0);
+        self.allocated = 0;
+        self.threshold = INITIAL_THRESHOLD.max(2 * self.stats().total());
+        freed
+    }
+
+    /// Counts the tracked objects that are still alive.
+    pub fn stats(&self) -> HeapStats {
+        HeapStats {
+            environments: self.environments.iter().filter(|environment| environment.strong_count() > 0).count(),
+            arrays: self.arrays.iter().filter(|array| array.strong_count() > 0).count(),
+            peak: self.peak
+        }
+    }
+}
+
+/// An object that can hold references to others.
+enum Node {
+    Environment(Rc<RefCell<Environment>>),
+    Array(Rc<Array>),
+    Function(Rc<AliceFunction>),
+    Module(Rc<NativeModule>)
+}
+
+impl Node {
+    fn address(&self) -> *const () {
+        match self {
+            Node::Environment(environment) => Rc::as_ptr(environment) as *const (),
+            Node::Array(array) => Rc::as_ptr(array) as *const (),
+            Node::Function(function) => Rc::as_ptr(function) as *const (),
+            Node::Module(module) => Rc::as_ptr(module) as *const ()
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Node::Environment(environment) => Rc::strong_count(environment),
+            Node::Array(array) => Rc::strong_count(array),
+            Node::Function(function) => Rc::strong_count(function),
+            Node::Module(module) => Rc::strong_count(module)
+        }
+    }
+
+    /// Adds the objects this one references to `children`, or returns
+    /// `false` if it is borrowed mutably and can't be looked into.
+    fn children(&self, children: &mut Vec<Node>) -> bool {
+        match self {
+            Node::Environment(environment) => {
+                let Ok(environment) = environment.try_borrow() else { return false };
+                environment.values().iter().for_each(|value| child(children, value));
+                children.extend(environment.parent().map(Node::Environment));
+            }
+            Node::Array(array) => {
+                let Ok(array) = array.try_borrow() else { return false };
+                array.iter().for_each(|value| child(children, value));
+            }
+            Node::Function(function) => children.extend(function.closure.clone().map(Node::Environment)),
+            Node::Module(module) => module.members().for_each(|value| child(children, value))
+        }
+        true
+    }
+}
+
+fn child(children: &mut Vec<Node>, value: &AliceObject) {
+    match value {
+        AliceObject::Array(array) => children.push(Node::Array(array.clone())),
+        AliceObject::Function(function) => children.push(Node::Function(function.clone())),
+        AliceObject::Module(module) => children.push(Node::Module(module.clone())),
+        _ => ()
+    }
+}
+
+/// The objects reachable from the tracked ones and the references between
+/// them. Every node is held once, by `nodes`.
+#[derive(Default)]
+struct Graph {
+    nodes: Vec<Node>,
+    indices: HashMap<*const (), usize, BuildHasherDefault<AddressHasher>>,
+    /// References to each node from other nodes.
+    internal: Vec<usize>,
+    /// The nodes node `i` references are `edges[starts[i]..starts[i + 1]]`.
+    edges: Vec<usize>,
+    starts: Vec<usize>,
+    /// Nodes that are borrowed mutably, whose references are unknown.
+    opaque: Vec<bool>
+}
+
+impl Graph {
+    /// Adds `node` if it is new.
+    fn add(&mut self, node: Node) -> usize {
+        match self.indices.entry(node.address()) {
+            Entry::Occupied(entry) => *entry.get(),
+            Entry::Vacant(entry) => {
+                let index = self.nodes.len();
+                entry.insert(index);
+                self.nodes.push(node);
+                self.internal.push(0);
+                self.opaque.push(false);
+                index
+            }
+        }
+    }
+
+    /// Adds everything the nodes lead to, and the references between them.
+    fn link(&mut self) {
+        let mut children = Vec::new();
+        let mut index = 0;
+        // Nodes found along the way are added at the end, and linked in turn.
+        while index < self.nodes.len() {
+            self.starts.push(self.edges.len());
+            match self.nodes[index].children(&mut children) {
+                true => for child in children.drain(..) {
+                    let child = self.add(child);
+                    self.internal[child] += 1;
+                    self.edges.push(child);
+                },
+                false => self.opaque[index] = true
+            }
+            index += 1;
+        }
+        self.starts.push(self.edges.len());
+    }
+
+    /// Which nodes are reachable from one held from outside the graph.
+    fn mark(&self) -> Vec<bool> {
+        // A node's strong count includes the reference `nodes` holds.
+        let mut pending: Vec<usize> = (0..self.nodes.len())
+            .filter(|&index| self.opaque[index] || self.nodes[index].strong_count() > self.internal[index] + 1)
+            .collect();
+
+        let mut live = vec![false; self.nodes.len()];
+        pending.iter().for_each(|&index| live[index] = true);
+        while let Some(index) = pending.pop() {
+            for &child in &self.edges[self.starts[index]..self.starts[index + 1]] {
+                if !live[child] {
+                    live[child] = true;
+                    pending.push(child);
+                }
+            }
+        }
+        live
+    }
+}
+
+/// Hashes the addresses `Graph` looks nodes up by, which need no more than
+/// a multiplication to spread.
+#[derive(Default)]
+struct AddressHasher(u64);
+
+impl Hasher for AddressHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        bytes.iter().for_each(|&byte| self.write_u8(byte));
+    }
+
+    #[inline]
+    fn write_u8(&mut self, byte: u8) {
+        self.write_usize(byte.into());
+    }
+
+    #[inline]
+    fn write_usize(&mut self, address: usize) {
+        self.0 = (self.0 ^ address as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
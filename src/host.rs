@@ -0,0 +1,231 @@
+use std::{cell::{Cell, RefCell}, collections::{hash_map::RandomState, HashMap}, hash::{BuildHasher, Hasher}, io::{self, BufRead, Write}, time::{Duration, SystemTime, UNIX_EPOCH}};
+
+/// Everything a running program can reach outside of itself: output, input,
+/// the clock, randomness, files and environment variables. The native
+/// functions go through it, so an embedder can redirect or fake any of it.
+///
+/// Methods take `&self`, as the host is shared by the engine and every
+/// native function that uses it.
+pub trait Host: 'static {
+    fn write_stdout(&self, bytes: &[u8]) -> io::Result<()>;
+
+    fn write_stderr(&self, bytes: &[u8]) -> io::Result<()>;
+
+    /// The next line of input without its line ending, or `None` at the end
+    /// of input.
+    fn read_line(&self) -> io::Result<Option<String>>;
+
+    /// The time since the Unix epoch.
+    fn now(&self) -> Duration;
+
+    /// 64 random bits.
+    fn random(&self) -> u64;
+
+    fn read_file(&self, path: &str) -> io::Result<String>;
+
+    fn write_file(&self, path: &str, contents: &str) -> io::Result<()>;
+
+    /// The value of the environment variable `name`, if it is set.
+    fn env_var(&self, name: &str) -> Option<String>;
+
+    /// Every environment variable as `(name, value)`, in no particular
+    /// order.
+    fn env_vars(&self) -> Vec<(String, String)>;
+}
+
+/// The process's own stdio, clock, filesystem and environment.
+pub struct OsHost {
+    seed: Cell<u64>
+}
+
+impl Default for OsHost {
+    fn default() -> Self {
+        // `RandomState` is seeded randomly per process, which is all the
+        // entropy a scripting language's `random()` needs.
+        OsHost { seed: Cell::new(RandomState::new().build_hasher().finish()) }
+    }
+}
+
+impl Host for OsHost {
+    fn write_stdout(&self, bytes: &[u8]) -> io::Result<()> {
+        io::stdout().lock().write_all(bytes)
+    }
+
+    fn write_stderr(&self, bytes: &[u8]) -> io::Result<()> {
+        io::stderr().lock().write_all(bytes)
+    }
+
+    fn read_line(&self) -> io::Result<Option<String>> {
+        // A prompt written with `print` has no newline to flush it.
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        match io::stdin().lock().read_line(&mut line)? {
+            0 => Ok(None),
+            _ => Ok(Some(trim_newline(line)))
+        }
+    }
+
+    fn now(&self) -> Duration {
+        SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+    }
+
+    fn random(&self) -> u64 {
+        splitmix64(&self.seed)
+    }
+
+    fn read_file(&self, path: &str) -> io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    fn write_file(&self, path: &str, contents: &str) -> io::Result<()> {
+        std::fs::write(path, contents)
+    }
+
+    fn env_var(&self, name: &str) -> Option<String> {
+        std::env::var_os(name).map(|value| value.to_string_lossy().into_owned())
+    }
+
+    fn env_vars(&self) -> Vec<(String, String)> {
+        std::env::vars_os()
+            .map(|(name, value)| (name.to_string_lossy().into_owned(), value.to_string_lossy().into_owned()))
+            .collect()
+    }
+}
+
+/// A host that keeps everything in memory, for tests and sandboxes. It
+/// records all output, reads input, files and environment variables given
+/// to it up front, stands the clock still and produces the same random
+/// numbers every run.
+#[derive(Default)]
+pub struct MemoryHost {
+    stdout: RefCell<Vec<u8>>,
+    stderr: RefCell<Vec<u8>>,
+    stdin: RefCell<String>,
+    time: Cell<Duration>,
+    seed: Cell<u64>,
+    files: RefCell<HashMap<String, String>>,
+    env: HashMap<String, String>
+}
+
+impl MemoryHost {
+    #[inline]
+    pub fn new() -> MemoryHost {
+        MemoryHost::default()
+    }
+
+    /// Sets the input `read_line` returns.
+    pub fn with_stdin(self, input: &str) -> MemoryHost {
+        *self.stdin.borrow_mut() = input.to_owned();
+        self
+    }
+
+    pub fn with_file(self, path: &str, contents: &str) -> MemoryHost {
+        self.files.borrow_mut().insert(path.to_owned(), contents.to_owned());
+        self
+    }
+
+    /// Sets the environment variable `name`; there are none otherwise.
+    pub fn with_env(mut self, name: &str, value: &str) -> MemoryHost {
+        self.env.insert(name.to_owned(), value.to_owned());
+        self
+    }
+
+    pub fn with_seed(self, seed: u64) -> MemoryHost {
+        self.seed.set(seed);
+        self
+    }
+
+    /// Everything written to stdout so far.
+    pub fn stdout(&self) -> String {
+        String::from_utf8_lossy(&self.stdout.borrow()).into_owned()
+    }
+
+    /// Everything written to stderr so far.
+    pub fn stderr(&self) -> String {
+        String::from_utf8_lossy(&self.stderr.borrow()).into_owned()
+    }
+
+    /// The contents of `path`, if the program or `with_file` wrote it.
+    pub fn file(&self, path: &str) -> Option<String> {
+        self.files.borrow().get(path).cloned()
+    }
+
+    /// Sets the time `now` returns.
+    #[inline]
+    pub fn set_time(&self, time: Duration) {
+        self.time.set(time);
+    }
+}
+
+impl Host for MemoryHost {
+    fn write_stdout(&self, bytes: &[u8]) -> io::Result<()> {
+        self.stdout.borrow_mut().extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn write_stderr(&self, bytes: &[u8]) -> io::Result<()> {
+        self.stderr.borrow_mut().extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn read_line(&self) -> io::Result<Option<String>> {
+        let mut stdin = self.stdin.borrow_mut();
+        if stdin.is_empty() {
+            return Ok(None);
+        }
+
+        let end = stdin.find('\n').map_or(stdin.len(), |end| end + 1);
+        let line: String = stdin.drain(..end).collect();
+        Ok(Some(trim_newline(line)))
+    }
+
+    fn now(&self) -> Duration {
+        self.time.get()
+    }
+
+    fn random(&self) -> u64 {
+        splitmix64(&self.seed)
+    }
+
+    fn read_file(&self, path: &str) -> io::Result<String> {
+        match self.file(path) {
+            Some(contents) => Ok(contents),
+            None => Err(io::Error::new(io::ErrorKind::NotFound, "No such file"))
+        }
+    }
+
+    fn write_file(&self, path: &str, contents: &str) -> io::Result<()> {
+        self.files.borrow_mut().insert(path.to_owned(), contents.to_owned());
+        Ok(())
+    }
+
+    fn env_var(&self, name: &str) -> Option<String> {
+        self.env.get(name).cloned()
+    }
+
+    fn env_vars(&self) -> Vec<(String, String)> {
+        self.env.iter().map(|(name, value)| (name.clone(), value.clone())).collect()
+    }
+}
+
+fn trim_newline(mut line: String) -> String {
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+    line
+}
+
+/// Steps the SplitMix64 generator whose state is `seed`.
+fn splitmix64(seed: &Cell<u64>) -> u64 {
+    let state = seed.get().wrapping_add(0x9e37_79b9_7f4a_7c15);
+    seed.set(state);
+
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -1,8 +1,46 @@
-mod token;
-mod error;
-mod ast;
 mod environment;
+mod function;
+mod chunk;
+mod ops;
+mod string;
+mod unicode;
+mod format;
+mod display;
+mod engine;
+mod ast;
+mod symbol;
+mod parse;
+mod resolver;
+mod optimizer;
+mod tree;
+mod compiler;
+mod vm;
+mod heap;
+mod program;
 
+pub mod token;
+pub mod error;
 pub mod scanner;
+pub mod native;
+pub mod host;
+pub mod value;
 pub mod parser;
-pub mod interpreter;
\ No newline at end of file
+pub mod interpreter;
+
+pub use ast::Stmt;
+pub use engine::Engine;
+pub use error::AliceError as Error;
+pub use heap::HeapStats;
+pub use tree::Interpreter;
+pub use program::Program;
+pub use value::Value;
+pub use vm::Vm;
+
+/// Runs `source` in a fresh interpreter and returns the value of its last
+/// statement if that is a bare expression, and `nil` otherwise.
+///
+/// A call to `exit(status)` ends the program with an error whose kind is
+/// `ErrorKind::Exit(status)`; it is not a failure of the program.
+pub fn eval(source: &str) -> Result<Value, Error> {
+    Interpreter::new().eval_str(source)
+}
@@ -1,71 +1,281 @@
-use std::io::Write;
-use alice::{scanner::Scanner, parser::Parser, interpreter::Interpreter};
+mod editor;
+mod repl;
 
-fn main() -> std::io::Result<()> {
-    let args = std::env::args().collect::<Vec<String>>();
+use std::io::{Read, Write};
+use alice::{Engine as _, Interpreter, Program, Vm, scanner::{self, Scanner}, error::{AliceError, ErrorKind}};
 
-    if args.len() == 2 {
-        run_file(&args[1])?;
-    } else {
-        run_prompt()?;
-    }
+const USAGE: &str = "\
+usage: alice [options] [command] [script [args...]]
 
-    Ok(())
-}
+commands:
+    run       run the script (the default)
+    check     scan, parse and resolve the script without running it
+    tokens    print the script's tokens
+    ast       print the script's syntax tree
+    repl      start an interactive session (the default without a script)
 
-fn run_prompt() -> std::io::Result<()> {
-    loop {
-        let mut code = String::new();
-        print!("> ");
-        std::io::stdout().flush()?;
+script:
+    <file>    read the script from a file
+    -         read the script from stdin
+    -e <code> run <code>
 
-        std::io::stdin().read_line(&mut code).unwrap();
+options:
+    --engine=tree|vm           choose the execution engine (default: tree)
+    --optimize, --no-optimize  turn the AST optimizer on or off (default: off)
+    --error-format=human|json  how errors are reported (default: human)
+    --dump-tokens              print the tokens before running
+    --dump-ast                 print the syntax tree before running
+    -h, --help                 print this message
 
-        let source = code.into_bytes();
-        run(source);
-    }
+Arguments after the script are passed to it.
+
+exit status:
+    0         success
+    64        bad command line
+    65        syntax error (the script couldn't be scanned)
+    66        parse error (the script couldn't be parsed or resolved)
+    70        runtime error
+    74        the script couldn't be read
+A script that calls exit(status) exits with that status.";
+
+// Exit statuses, listed in `USAGE`. Usage, runtime and I/O errors follow the
+// BSD `sysexits.h` convention; syntax and parse errors each get their own.
+const EXIT_USAGE: i32 = 64;
+const EXIT_SYNTAX: i32 = 65;
+const EXIT_PARSE: i32 = 66;
+const EXIT_RUNTIME: i32 = 70;
+const EXIT_IO: i32 = 74;
+
+// The tree engine recurses on the native stack for each call, which takes
+// more than the main thread has for its deepest programs in debug builds.
+const STACK_SIZE: usize = 64 * 1024 * 1024;
+
+// How many calls deep scripts may go, which the stack above has room for
+// on either engine.
+const MAX_CALL_DEPTH: usize = 1000;
 
-    Ok(())
+#[derive(Clone, Copy, PartialEq)]
+enum ErrorFormat {
+    Human,
+    Json
 }
 
-fn run_file(path: &str) -> std::io::Result<()> {
-    let code = String::new();
+#[derive(Clone, Copy, PartialEq)]
+enum Engine {
+    Tree,
+    Vm
+}
+
+#[derive(Clone, Copy)]
+struct Options {
+    format: ErrorFormat,
+    engine: Engine,
+    optimize: bool,
+    dump_tokens: bool,
+    dump_ast: bool
+}
 
-    let source = std::fs::read_to_string(path)?.into_bytes();
+#[derive(Clone, Copy, PartialEq)]
+enum Command {
+    Run,
+    Check,
+    Tokens,
+    Ast,
+    Repl
+}
 
-    run(source);
+enum Source {
+    File(String),
+    Stdin,
+    Code(String)
+}
 
-    Ok(())
+fn main() {
+    let cli = std::thread::Builder::new().stack_size(STACK_SIZE).spawn(cli).expect("failed to spawn the main thread");
+    let status = cli.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+    std::process::exit(status);
 }
 
-fn run(source: Vec<u8>) {
-    let mut scanner = Scanner::new(source);
-    let tokens = match scanner.scan_tokens() {
-        Ok(tokens) => {
-            tokens.iter().for_each(|token| println!("{:?}", token));
-            tokens
+fn cli() -> i32 {
+    let mut options = Options { format: ErrorFormat::Human, engine: Engine::Tree, optimize: false, dump_tokens: false, dump_ast: false };
+    let mut command = None;
+    let mut source = None;
+
+    let mut args = std::env::args().skip(1);
+    while source.is_none() {
+        let Some(arg) = args.next() else {
+            break;
+        };
+
+        if let Some(format) = arg.strip_prefix("--error-format=") {
+            options.format = match format {
+                "human" => ErrorFormat::Human,
+                "json" => ErrorFormat::Json,
+                other => usage(format!("unknown error format '{other}', expected 'human' or 'json'."))
+            };
+        } else if let Some(engine) = arg.strip_prefix("--engine=") {
+            options.engine = match engine {
+                "tree" => Engine::Tree,
+                "vm" => Engine::Vm,
+                other => usage(format!("unknown engine '{other}', expected 'tree' or 'vm'."))
+            };
+        } else if arg == "--optimize" {
+            options.optimize = true;
+        } else if arg == "--no-optimize" {
+            options.optimize = false;
+        } else if arg == "--dump-tokens" {
+            options.dump_tokens = true;
+        } else if arg == "--dump-ast" {
+            options.dump_ast = true;
+        } else if arg == "-h" || arg == "--help" {
+            println!("{USAGE}");
+            return 0;
+        } else if arg == "-e" {
+            match args.next() {
+                Some(code) => source = Some(Source::Code(code)),
+                None => usage("'-e' expects the code to run.")
+            }
+        } else if arg == "-" {
+            source = Some(Source::Stdin);
+        } else if arg.starts_with('-') {
+            usage(format!("unknown option '{arg}'."));
+        } else if command.is_none() && is_command(&arg) {
+            command = Some(match arg.as_str() {
+                "run" => Command::Run,
+                "check" => Command::Check,
+                "tokens" => Command::Tokens,
+                "ast" => Command::Ast,
+                _ => Command::Repl
+            });
+        } else {
+            source = Some(Source::File(arg));
+        }
+    }
+
+    match (command, source) {
+        (None | Some(Command::Repl), None) => match repl::Repl::new(options).run() {
+            Ok(status) => status,
+            Err(e) => {
+                eprintln!("{e}");
+                EXIT_IO
+            }
         },
-        Err(errors) => {
-            errors.iter().for_each(|e| println!("{e}"));
-            return;
+        (Some(Command::Repl), Some(_)) => usage("'repl' does not take a script."),
+        (Some(_), None) => usage("expected a script: a file, '-' or '-e <code>'."),
+        (command, Some(source)) => execute(command.unwrap_or(Command::Run), source, args.collect(), options)
+    }
+}
+
+fn is_command(arg: &str) -> bool {
+    matches!(arg, "run" | "check" | "tokens" | "ast" | "repl")
+}
+
+fn usage(message: impl std::fmt::Display) -> ! {
+    eprintln!("{message}\n\n{USAGE}");
+    std::process::exit(EXIT_USAGE);
+}
+
+/// Runs `command` on the script, returning the process exit status.
+fn execute(command: Command, source: Source, args: Vec<String>, options: Options) -> i32 {
+    let (bytes, file) = match &source {
+        Source::File(path) => (std::fs::read(path), Some(path.as_str())),
+        Source::Stdin => {
+            let mut bytes = Vec::new();
+            (std::io::stdin().read_to_end(&mut bytes).map(|_| bytes), None)
         }
+        Source::Code(code) => (Ok(code.as_bytes().to_vec()), None)
     };
 
-    let mut parser = Parser::new(tokens);
-    let ast = match parser.parse() {
-        Ok(ast) => {
-            ast.iter().for_each(|node| println!("{:?}", node));
-            ast
+    let bytes = match bytes {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("can't read '{}': {e}.", file.unwrap_or("<stdin>"));
+            return EXIT_IO;
         }
+    };
+
+    let result = scanner::decode(&bytes).and_then(|source| run(command, source, file, args, options));
+    match result {
+        Ok(()) => 0,
+        Err(AliceError { kind: ErrorKind::Exit(status), .. }) => status,
         Err(e) => {
-            println!("{e}");
-            return;
+            let status = exit_status(&e);
+            report(e, file, options.format);
+            status
+        }
+    }
+}
+
+fn exit_status(error: &AliceError) -> i32 {
+    match error.kind {
+        ErrorKind::Syntax => EXIT_SYNTAX,
+        ErrorKind::Parse => EXIT_PARSE,
+        ErrorKind::Runtime => EXIT_RUNTIME,
+        ErrorKind::Exit(status) => status
+    }
+}
+
+fn report(error: AliceError, file: Option<&str>, format: ErrorFormat) {
+    let error = match file {
+        Some(file) => error.with_file(file),
+        None => error
+    };
+
+    // Nothing sensible is left to do if even the error can't be written
+    // (say, stderr is a closed pipe).
+    let _ = match format {
+        ErrorFormat::Human => writeln!(std::io::stderr(), "{error}"),
+        ErrorFormat::Json => writeln!(std::io::stderr(), "{}", error.to_json())
+    };
+}
+
+/// Scans, parses and (for `Command::Run`) runs `source`. All but the last
+/// of several syntax errors are reported here; the one returned decides the
+/// exit status.
+fn run(command: Command, source: &str, file: Option<&str>, args: Vec<String>, options: Options) -> Result<(), AliceError> {
+    let tokens = match Scanner::new(source).scan_tokens() {
+        Ok(tokens) => tokens,
+        Err(mut errors) => {
+            let last = errors.pop().expect("a failed scan has at least one error");
+            errors.into_iter().for_each(|e| report(e, file, options.format));
+            return Err(last);
         }
     };
 
-    let mut interpreter = Interpreter::new();
-    match interpreter.interpret(ast) {
-        Ok(_) => (),
-        Err(e) => println!("{e}")
+    if command == Command::Tokens || options.dump_tokens {
+        tokens.iter().for_each(|token| println!("{:?}", token));
+        if command == Command::Tokens {
+            return Ok(());
+        }
+    }
+
+    let mut program = Program::parse(source, tokens)?;
+
+    if command == Command::Ast || options.dump_ast {
+        print!("{:?}", program);
+        if command == Command::Ast {
+            return Ok(());
+        }
+    }
+
+    if command == Command::Check {
+        return program.check();
+    }
+
+    match options.engine {
+        Engine::Tree => {
+            let mut interpreter = Interpreter::new();
+            interpreter.set_args(args);
+            interpreter.set_optimize(options.optimize);
+            interpreter.set_max_call_depth(MAX_CALL_DEPTH);
+            interpreter.run(program)
+        }
+        Engine::Vm => {
+            let mut vm = Vm::new();
+            vm.set_args(args);
+            vm.set_optimize(options.optimize);
+            vm.set_max_call_depth(MAX_CALL_DEPTH);
+            vm.run(program)
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,371 @@
+use std::{collections::HashMap, fmt::{Debug, Display}, io, rc::Rc};
+
+use crate::{ast::{AliceObject, CallSite}, environment::Globals, display, error::AliceError, format, heap::Heap, host::Host, ops, symbol::Symbol, value::{Args, Value}};
+
+/// How many arguments a native function takes. A plain number converts to
+/// `Exact`; `AtLeast(0)` makes a function variadic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize)
+}
+
+impl Arity {
+    #[inline]
+    pub fn accepts(self, count: usize) -> bool {
+        match self {
+            Arity::Exact(arity) => count == arity,
+            Arity::AtLeast(arity) => count >= arity
+        }
+    }
+
+    /// The message for a call with `count` arguments that this arity
+    /// doesn't accept.
+    pub(crate) fn mismatch(self, count: usize) -> String {
+        let (Arity::Exact(arity) | Arity::AtLeast(arity)) = self;
+        let noun = if arity == 1 { "argument" } else { "arguments" };
+        format!("Expected {self} {noun} but got {count}.")
+    }
+}
+
+impl From<usize> for Arity {
+    #[inline]
+    fn from(arity: usize) -> Self {
+        Arity::Exact(arity)
+    }
+}
+
+impl Display for Arity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arity::Exact(arity) => write!(f, "{arity}"),
+            Arity::AtLeast(arity) => write!(f, "at least {arity}")
+        }
+    }
+}
+
+/// The keyword arguments a native function takes.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Keywords {
+    Only(&'static [&'static str]),
+    Any
+}
+
+impl Keywords {
+    fn accepts(self, name: &str) -> bool {
+        match self {
+            Keywords::Only(names) => names.contains(&name),
+            Keywords::Any => true
+        }
+    }
+}
+
+type NativeFn = dyn Fn(&mut Heap, &CallSite, &[AliceObject]) -> Result<AliceObject, AliceError>;
+
+/// A function implemented in Rust, callable from Alice like any other.
+pub struct NativeFunction {
+    name: Symbol,
+    /// How many positional arguments it takes.
+    pub arity: Arity,
+    keywords: Keywords,
+    function: Box<NativeFn>
+}
+
+impl NativeFunction {
+    /// A function reading its arguments from `Args` and returning a
+    /// `Value`. Arrays are copied out of the arguments as they are read and
+    /// into the program as they are returned, so the program can't see
+    /// changes `function` makes to them.
+    pub fn new(name: &str, arity: Arity, function: impl Fn(Args) -> Result<Value, AliceError> + 'static) -> NativeFunction {
+        NativeFunction::with_call(name, arity, move |heap, _, arguments| function(Args(arguments)).map(|value| heap.value(value)))
+    }
+
+    /// Like `new`, for the builtins, which work on the engines' own values.
+    pub(crate) fn builtin(name: &str, arity: Arity, function: impl Fn(&[AliceObject]) -> Result<AliceObject, AliceError> + 'static) -> NativeFunction {
+        NativeFunction::with_call(name, arity, move |_, _, arguments| function(arguments))
+    }
+
+    /// Like `new`, for functions that need the call site, say to know its
+    /// line or to look up keyword arguments, or the heap, to allocate the
+    /// arrays they return. The keyword arguments come after the positional
+    /// ones.
+    pub(crate) fn with_call(name: &str, arity: Arity, function: impl Fn(&mut Heap, &CallSite, &[AliceObject]) -> Result<AliceObject, AliceError> + 'static) -> NativeFunction {
+        NativeFunction { name: Symbol::intern(name), arity, keywords: Keywords::Only(&[]), function: Box::new(function) }
+    }
+
+    /// Accepts the `keywords` arguments, which it takes none of otherwise.
+    pub(crate) fn keywords(mut self, keywords: Keywords) -> NativeFunction {
+        self.keywords = keywords;
+        self
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    /// Checks the arguments and calls the function. Errors it returns
+    /// without a position are reported at the call.
+    pub(crate) fn call(&self, heap: &mut Heap, site: &CallSite, arguments: &[AliceObject]) -> Result<AliceObject, AliceError> {
+        if let Some(keyword) = site.keywords.iter().find(|keyword| !self.keywords.accepts(keyword.symbol().as_str())) {
+            return Err(AliceError::runtime(format!("Unexpected keyword argument '{}'.", keyword.symbol()), keyword));
+        }
+
+        let positional = site.positional(arguments.len());
+        if !self.arity.accepts(positional) {
+            return Err(AliceError::runtime(self.arity.mismatch(positional), &site.paren));
+        }
+
+        (self.function)(heap, site, arguments).map_err(|e| match e.span {
+            Some(_) => e,
+            None => e.at(&site.paren)
+        })
+    }
+}
+
+impl Debug for NativeFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+/// A named set of native functions and values, defined as one global and
+/// reached from scripts as `name.member`.
+pub struct NativeModule {
+    name: Symbol,
+    members: HashMap<Symbol, AliceObject>
+}
+
+impl NativeModule {
+    pub fn new(name: &str) -> NativeModule {
+        NativeModule { name: Symbol::intern(name), members: HashMap::new() }
+    }
+
+    pub fn function(mut self, name: &str, arity: impl Into<Arity>, function: impl Fn(Args) -> Result<Value, AliceError> + 'static) -> NativeModule {
+        let function = NativeFunction::new(name, arity.into(), function);
+        self.members.insert(function.name.clone(), AliceObject::Native(Rc::new(function)));
+        self
+    }
+
+    pub fn value(mut self, name: &str, value: impl Into<Value>) -> NativeModule {
+        self.members.insert(Symbol::intern(name), value.into().into());
+        self
+    }
+
+    /// Nests `module` inside this one, as `name.module.member`.
+    pub fn module(mut self, module: NativeModule) -> NativeModule {
+        self.members.insert(module.name.clone(), AliceObject::Module(Rc::new(module)));
+        self
+    }
+
+    #[inline]
+    pub fn name(&self) -> &str {
+        self.name.as_str()
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, name: &Symbol) -> Option<AliceObject> {
+        self.members.get(name).cloned()
+    }
+
+    #[inline]
+    pub(crate) fn members(&self) -> impl Iterator<Item = &AliceObject> {
+        self.members.values()
+    }
+}
+
+impl Debug for NativeModule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<module {}>", self.name)
+    }
+}
+
+/// Defines `function` as the global `name`.
+pub(crate) fn register(globals: &mut Globals, name: &str, arity: Arity, function: impl Fn(Args) -> Result<Value, AliceError> + 'static) {
+    define(globals, NativeFunction::new(name, arity, function));
+}
+
+/// Defines `module` as the global of the same name.
+pub(crate) fn register_module(globals: &mut Globals, module: NativeModule) {
+    globals.define(module.name.clone(), AliceObject::Module(Rc::new(module)));
+}
+
+/// Tracks the arrays among `module`'s values, and its nested modules', on
+/// `heap`.
+pub(crate) fn adopt_module(heap: &mut Heap, module: &NativeModule) {
+    module.members().for_each(|member| match member {
+        AliceObject::Module(module) => adopt_module(heap, module),
+        member => heap.adopt(member)
+    });
+}
+
+/// Widest line `dbg` writes before spreading an array over several lines.
+const DBG_WIDTH: usize = 60;
+
+/// Defines the functions every program starts out with. Those that reach
+/// outside of the program go through `host`.
+pub(crate) fn define_builtins(globals: &mut Globals, host: Rc<dyn Host>) {
+    define_args(globals, Vec::new());
+
+    for (name, end, stderr) in [("print", "", false), ("println", "\n", false), ("eprint", "", true), ("eprintln", "\n", true)] {
+        let host = host.clone();
+        let function = NativeFunction::with_call(name, Arity::AtLeast(0), move |_, site, arguments| {
+            let text = print(site, arguments, end)?;
+            match stderr {
+                false => written(host.write_stdout(text.as_bytes())),
+                true => written(host.write_stderr(text.as_bytes()))
+            }
+        });
+        define(globals, function.keywords(Keywords::Only(&["sep"])));
+    }
+
+    let dbg_host = host.clone();
+    define(globals, NativeFunction::with_call("dbg", Arity::AtLeast(0), move |heap, site, arguments| {
+        let line = site.paren.line;
+        let text = match arguments {
+            [] => format!("[line {}]\n", line),
+            arguments => arguments.iter().enumerate().map(|(i, argument)| match site.source.get(i) {
+                Some(source) => format!("[line {}] {} = {}\n", line, source, display::pretty(argument, DBG_WIDTH)),
+                None => format!("[line {}] {}\n", line, display::pretty(argument, DBG_WIDTH))
+            }).collect()
+        };
+        written(dbg_host.write_stderr(text.as_bytes()))?;
+
+        match arguments {
+            [] => Ok(AliceObject::Nil),
+            [argument] => Ok(argument.clone()),
+            arguments => Ok(heap.array(arguments.to_vec()))
+        }
+    }));
+
+    let function = NativeFunction::with_call("format", Arity::AtLeast(1), |_, site, arguments| {
+        let AliceObject::String(template) = &arguments[0] else {
+            return Err(error(format!("The format string must be a string, not {}.", arguments[0].type_name())));
+        };
+
+        let names: Vec<Symbol> = site.keywords.iter().map(|keyword| keyword.symbol()).collect();
+        let names: Vec<&str> = names.iter().map(Symbol::as_str).collect();
+        let formatted = format::format(template, &arguments[1..], &names).map_err(error)?;
+
+        let positional = site.positional(arguments.len()) - 1;
+        match formatted.used.iter().position(|used| !used) {
+            Some(unused) if unused < positional => Err(error(format!("Argument {} is never used by the format string.", unused))),
+            Some(unused) => {
+                let keyword = &site.keywords[unused - positional];
+                Err(AliceError::runtime(format!("Keyword argument '{}' is never used by the format string.", keyword.symbol()), keyword))
+            }
+            None => Ok(AliceObject::String(formatted.text.into()))
+        }
+    });
+    define(globals, function.keywords(Keywords::Any));
+
+    let read_line_host = host.clone();
+    define(globals, NativeFunction::builtin("read_line", Arity::Exact(0), move |_| match read_line_host.read_line() {
+        Ok(line) => Ok(line.map_or(AliceObject::Nil, |line| AliceObject::String(line.into()))),
+        Err(e) => Err(error(format!("Can't read input: {e}.")))
+    }));
+
+    let clock_host = host.clone();
+    define(globals, NativeFunction::builtin("clock", Arity::Exact(0), move |_| Ok(AliceObject::F64(clock_host.now().as_secs_f64()))));
+
+    let random_host = host.clone();
+    define(globals, NativeFunction::builtin("random", Arity::Exact(0), move |_| {
+        // The top 53 bits, as many as an f64 holds exactly, scaled to [0, 1).
+        Ok(AliceObject::F64((random_host.random() >> 11) as f64 / (1u64 << 53) as f64))
+    }));
+
+    let read_file_host = host.clone();
+    define(globals, NativeFunction::builtin("read_file", Arity::Exact(1), move |arguments| match &arguments[0] {
+        AliceObject::String(path) => match read_file_host.read_file(path) {
+            Ok(contents) => Ok(AliceObject::String(contents.into())),
+            Err(e) => Err(error(format!("Can't read '{}': {e}.", path)))
+        },
+        other => Err(error(format!("The path must be a string, not {}.", other.type_name())))
+    }));
+
+    let write_file_host = host.clone();
+    define(globals, NativeFunction::builtin("write_file", Arity::Exact(2), move |arguments| match arguments {
+        [AliceObject::String(path), AliceObject::String(contents)] => match write_file_host.write_file(path, contents) {
+            Ok(()) => Ok(AliceObject::Nil),
+            Err(e) => Err(error(format!("Can't write '{}': {e}.", path)))
+        },
+        [AliceObject::String(_), other] => Err(error(format!("The contents must be a string, not {}.", other.type_name()))),
+        [other, _] => Err(error(format!("The path must be a string, not {}.", other.type_name()))),
+        _ => unreachable!("the arity is checked before the call")
+    }));
+
+    let env_host = host.clone();
+    define(globals, NativeFunction::builtin("env", Arity::Exact(1), move |arguments| match &arguments[0] {
+        AliceObject::String(name) => Ok(env_host.env_var(name).map_or(AliceObject::Nil, |value| AliceObject::String(value.into()))),
+        other => Err(error(format!("The variable name must be a string, not {}.", other.type_name())))
+    }));
+
+    define(globals, NativeFunction::with_call("env_vars", Arity::Exact(0), move |heap, _, _| {
+        let mut vars = host.env_vars();
+        vars.sort();
+
+        let pairs = vars.into_iter()
+            .map(|(name, value)| heap.array(vec![AliceObject::String(name.into()), AliceObject::String(value.into())]))
+            .collect();
+        Ok(heap.array(pairs))
+    }));
+
+    define(globals, NativeFunction::builtin("exit", Arity::Exact(1), |arguments| match arguments[0] {
+        AliceObject::I64(status) => match u8::try_from(status) {
+            Ok(status) => Err(AliceError::exit(status.into())),
+            Err(_) => Err(error(format!("Exit status {} is out of range (0 to 255).", status)))
+        },
+        ref other => Err(error(format!("The exit status must be an i64, not {}.", other.type_name())))
+    }));
+}
+
+/// (Re)defines `args()` to return `args`, the arguments passed to the script.
+pub(crate) fn define_args(globals: &mut Globals, args: Vec<String>) {
+    let args: Rc<[String]> = args.into();
+    define(globals, NativeFunction::with_call("args", Arity::Exact(0), move |heap, _, _| {
+        Ok(heap.array(args.iter().map(|arg| AliceObject::String(arg.as_str().into())).collect()))
+    }));
+}
+
+/// The text `print` writes: the positional arguments separated by the `sep`
+/// keyword argument (a space by default), followed by `end`. With several
+/// arguments and a string first, that string is a format string as for
+/// `format`, and the arguments it doesn't use follow it. A lone argument is
+/// written as it is, braces and all.
+fn print(site: &CallSite, arguments: &[AliceObject], end: &str) -> Result<String, AliceError> {
+    let sep = match site.keyword(arguments, "sep") {
+        None => " ",
+        Some(AliceObject::String(sep)) => sep.as_str(),
+        Some(sep) => return Err(error(format!("The separator must be a string, not {}.", sep.type_name())))
+    };
+
+    let positional = &arguments[..site.positional(arguments.len())];
+    let mut text = Vec::new();
+    match positional {
+        [AliceObject::String(template), rest @ ..] if !rest.is_empty() => {
+            let formatted = format::format(template, rest, &[]).map_err(error)?;
+            text.push(formatted.text);
+            text.extend(rest.iter().zip(formatted.used).filter(|(_, used)| !used).map(|(argument, _)| ops::stringify(argument.clone())));
+        }
+        _ => text.extend(positional.iter().map(|argument| ops::stringify(argument.clone())))
+    }
+    Ok(text.join(sep) + end)
+}
+
+/// `nil`, or a runtime error if writing the output failed.
+fn written(result: io::Result<()>) -> Result<AliceObject, AliceError> {
+    match result {
+        Ok(()) => Ok(AliceObject::Nil),
+        Err(e) => Err(error(format!("Can't write output: {e}.")))
+    }
+}
+
+#[inline]
+fn define(globals: &mut Globals, function: NativeFunction) {
+    globals.define(function.name.clone(), AliceObject::Native(Rc::new(function)));
+}
+
+#[inline]
+fn error(message: String) -> AliceError {
+    AliceError::native(message)
+}
@@ -0,0 +1,221 @@
+use std::rc::Rc;
+
+use crate::{ast::{AliceObject, AliceRange}, display, string::AliceString, error::AliceError, token::{Token, TokenType}};
+
+// Value semantics shared by the tree-walking `Interpreter` and the bytecode
+// `Vm`, so both engines print and fail identically.
+
+#[inline]
+pub fn is_truthy(value: &AliceObject) -> bool {
+    match value {
+        AliceObject::Nil => false,
+        AliceObject::Boolean(bool) => *bool,
+        _ => true        
+    }
+}
+
+#[inline]
+pub fn is_equal(l: &AliceObject, r: &AliceObject) -> bool {
+    match (l, r) {
+        (AliceObject::Nil, AliceObject::Nil) => true,
+        (AliceObject::Boolean(l), AliceObject::Boolean(r)) => l == r,
+        (AliceObject::String(l), AliceObject::String(r)) => l == r,
+        (AliceObject::F64(l), AliceObject::F64(r)) => l == r,
+        (AliceObject::I64(l), AliceObject::I64(r)) => l == r,
+        (AliceObject::Function(l), AliceObject::Function(r)) => Rc::ptr_eq(l, r),
+        (AliceObject::Native(l), AliceObject::Native(r)) => Rc::ptr_eq(l, r),
+        (AliceObject::Module(l), AliceObject::Module(r)) => Rc::ptr_eq(l, r),
+        (AliceObject::Array(l), AliceObject::Array(r)) => Rc::ptr_eq(l, r),
+        _ => false
+    }
+}
+
+#[inline]
+pub fn stringify(value: AliceObject) -> String {
+    display::display(&value)
+}
+
+pub fn get_index(object: AliceObject, bracket: &Token, index: AliceObject) -> Result<AliceObject, AliceError> {
+    let AliceObject::Array(array) = object else {
+        return Err(AliceError::runtime(format!("Can only index arrays, not {}.", object.type_name()), bracket));
+    };
+    let position = position(&array.borrow(), bracket, index)?;
+
+    let value = array.borrow()[position].clone();
+    Ok(value)
+}
+
+pub fn set_index(object: AliceObject, bracket: &Token, index: AliceObject, value: AliceObject) -> Result<(), AliceError> {
+    let AliceObject::Array(array) = object else {
+        return Err(AliceError::runtime(format!("Can only index arrays, not {}.", object.type_name()), bracket));
+    };
+    let position = position(&array.borrow(), bracket, index)?;
+
+    array.borrow_mut()[position] = value;
+    Ok(())
+}
+
+pub fn get_member(object: AliceObject, name: &Token) -> Result<AliceObject, AliceError> {
+    let AliceObject::Module(module) = &object else {
+        return Err(AliceError::runtime(format!("Can only get members of modules, not {}.", object.type_name()), name));
+    };
+
+    let lexeme = name.symbol();
+    match module.get(&lexeme) {
+        Some(value) => Ok(value),
+        None => Err(AliceError::runtime(format!("Module '{}' has no member '{}'.", module.name(), lexeme), name))
+    }
+}
+
+fn position(array: &[AliceObject], bracket: &Token, index: AliceObject) -> Result<usize, AliceError> {
+    match index {
+        AliceObject::I64(i) if i >= 0 && (i as usize) < array.len() => Ok(i as usize),
+        AliceObject::I64(i) => {
+            let msg = format!("Index {} out of bounds for array of length {}.", i, array.len());
+            Err(AliceError::runtime(msg, bracket))
+        }
+        index => Err(AliceError::runtime(format!("Index must be an i64, not {}.", index.type_name()), bracket))
+    }
+}
+
+pub fn unary(operator: &Token, value: AliceObject) -> Result<AliceObject, AliceError> {
+    let v = match operator.r#type {
+        TokenType::Bang => AliceObject::Boolean(!is_truthy(&value)),
+        TokenType::Minus => {
+            if let AliceObject::F64(num) = value {
+                AliceObject::F64(-num)
+            } else if let AliceObject::I64(num) = value {
+                return checked(num.checked_neg(), operator);
+            } else {
+                let msg = format!("Operand must be a number, not {}.", value.type_name());
+                return Err(AliceError::runtime(msg, operator));
+            }
+        }
+        _ => AliceObject::Nil
+    };
+
+    Ok(v)
+}
+
+pub fn binary(left: AliceObject, operator: &Token, right: AliceObject) -> Result<AliceObject, AliceError> {
+    match operator.r#type {
+        TokenType::BangEqual => Ok(AliceObject::Boolean(!is_equal(&left, &right))),
+        TokenType::EqualEqual => Ok(AliceObject::Boolean(is_equal(&left, &right))),
+        TokenType::Greater => {
+            if let (AliceObject::F64(l), AliceObject::F64(r)) = (&left, &right) {
+                Ok(AliceObject::Boolean(l > r))
+            } else if let (AliceObject::I64(l), AliceObject::I64(r)) = (&left, &right) {
+                Ok(AliceObject::Boolean(l > r))
+            } else {
+                Err(operands(&left, operator, &right))
+            }
+        },
+        TokenType::GreaterEqual => {
+            if let (AliceObject::F64(l), AliceObject::F64(r)) = (&left, &right) {
+                Ok(AliceObject::Boolean(l >= r))
+            } else if let (AliceObject::I64(l), AliceObject::I64(r)) = (&left, &right) {
+                Ok(AliceObject::Boolean(l >= r))
+            } else {
+                Err(operands(&left, operator, &right))
+            }
+        },
+        TokenType::Less => {
+            if let (AliceObject::F64(l), AliceObject::F64(r)) = (&left, &right) {
+                Ok(AliceObject::Boolean(l < r))
+            } else if let (AliceObject::I64(l), AliceObject::I64(r)) = (&left, &right) {
+                Ok(AliceObject::Boolean(l < r))
+            } else {
+                Err(operands(&left, operator, &right))
+            }
+        },
+        TokenType::LessEqual => {
+            if let (AliceObject::F64(l), AliceObject::F64(r)) = (&left, &right) {
+                Ok(AliceObject::Boolean(l <= r))
+            } else if let (AliceObject::I64(l), AliceObject::I64(r)) = (&left, &right) {
+                Ok(AliceObject::Boolean(l <= r))
+            } else {
+                Err(operands(&left, operator, &right))
+            }
+        },
+        TokenType::Minus => {
+            if let (AliceObject::F64(l), AliceObject::F64(r)) = (&left, &right) {
+                Ok(AliceObject::F64(l - r))
+            } else if let (AliceObject::I64(l), AliceObject::I64(r)) = (&left, &right) {
+                checked(l.checked_sub(*r), operator)
+            } else {
+                Err(operands(&left, operator, &right))
+            }
+        }
+        TokenType::Plus => {
+            match (&left, &right) {
+                (AliceObject::String(l), AliceObject::String(r)) => Ok(AliceObject::String(AliceString::format(format_args!("{l}{r}")))),
+                (AliceObject::String(l), AliceObject::F64(r)) => Ok(AliceObject::String(AliceString::format(format_args!("{l}{}", display::float(*r))))),
+                (AliceObject::String(l), AliceObject::I64(r)) => Ok(AliceObject::String(AliceString::format(format_args!("{l}{r}")))),
+                (AliceObject::F64(l), AliceObject::F64(r)) => Ok(AliceObject::F64(l + r)),
+                (AliceObject::I64(l), AliceObject::I64(r)) => checked(l.checked_add(*r), operator),
+                _ => {
+                    let msg = format!("Operands must be numbers of the same type or start with a string, not {} and {}.", left.type_name(), right.type_name());
+                    Err(AliceError::runtime(msg, operator))
+                }
+            }
+        }
+        TokenType::Star => {
+            if let (AliceObject::F64(l), AliceObject::F64(r)) = (&left, &right) {
+                Ok(AliceObject::F64(l * r))
+            } else if let (AliceObject::I64(l), AliceObject::I64(r)) = (&left, &right) {
+                checked(l.checked_mul(*r), operator)
+            } else {
+                Err(operands(&left, operator, &right))
+            }
+        }
+        TokenType::Slash => {
+            if let (AliceObject::F64(l), AliceObject::F64(r)) = (&left, &right) {
+                Ok(AliceObject::F64(l / r))
+            } else if let (AliceObject::I64(l), AliceObject::I64(r)) = (&left, &right) {
+                if *r == 0 {
+                    return Err(AliceError::runtime("Division by zero.", operator));
+                }
+                checked(l.checked_div(*r), operator)
+            } else {
+                Err(operands(&left, operator, &right))
+            }
+        }
+        TokenType::PercentSign => {
+            if let (AliceObject::F64(l), AliceObject::F64(r)) = (&left, &right) {
+                Ok(AliceObject::F64(l % r))
+            } else if let (AliceObject::I64(l), AliceObject::I64(r)) = (&left, &right) {
+                if *r == 0 {
+                    return Err(AliceError::runtime("Division by zero.", operator));
+                }
+                checked(l.checked_rem(*r), operator)
+            } else {
+                Err(operands(&left, operator, &right))
+            }
+        }
+        _ => {
+            Ok(AliceObject::Nil)
+        }
+    }
+}
+
+fn operands(left: &AliceObject, operator: &Token, right: &AliceObject) -> AliceError {
+    let msg = format!("Operands must be numbers of the same type, not {} and {}.", left.type_name(), right.type_name());
+    AliceError::runtime(msg, operator)
+}
+
+#[inline]
+fn checked(value: Option<i64>, operator: &Token) -> Result<AliceObject, AliceError> {
+    match value {
+        Some(value) => Ok(AliceObject::I64(value)),
+        None => Err(AliceError::runtime("Integer overflow.", operator))
+    }
+}
+
+pub fn range(start: AliceObject, dots: &Token, end: AliceObject) -> Result<AliceObject, AliceError> {
+    if let (AliceObject::I64(l), AliceObject::I64(r)) = (&start, &end) {
+        Ok(AliceObject::Range(Rc::new(AliceRange { start: *l, end: *r })))
+    } else {
+        let msg = format!("Range bounds must be i64s, not {} and {}.", start.type_name(), end.type_name());
+        Err(AliceError::runtime(msg, dots))
+    }
+}
@@ -0,0 +1,193 @@
+use std::rc::Rc;
+
+use crate::{ast::{Stmt, Expr, AliceObject}, ops, token::TokenType};
+
+/// Optional pass run after the `Resolver` that simplifies the AST before
+/// execution:
+///
+/// - folds unary, binary, logical and grouping expressions over literals,
+/// - pre-computes ranges and array literals whose parts are all constant,
+/// - replaces `if` statements on a constant condition with the branch taken,
+/// - drops statements that follow a `return` in the same block.
+///
+/// An operation that would fail at runtime (say `1 / 0`) is left in place
+/// so the error is still reported, at the same position. Blocks are never
+/// added or removed around code that declares variables, so the resolver's
+/// bindings stay valid.
+#[derive(Default)]
+pub struct Optimizer;
+
+impl Optimizer {
+    #[inline]
+    pub fn new() -> Optimizer {
+        Optimizer
+    }
+
+    pub fn optimize(&mut self, statements: &mut Vec<Stmt>) {
+        self.optimize_stmts(statements);
+    }
+
+    fn optimize_stmts(&mut self, statements: &mut Vec<Stmt>) {
+        for stmt in statements.iter_mut() {
+            self.optimize_stmt(stmt);
+        }
+
+        if let Some(index) = statements.iter().position(|stmt| matches!(stmt, Stmt::Return { .. })) {
+            statements.truncate(index + 1);
+        }
+    }
+
+    fn optimize_stmt(&mut self, stmt: &mut Stmt) {
+        match stmt {
+            Stmt::Return { value: expression, .. } |
+            Stmt::Var { initializer: expression, .. } => {
+                if let Some(expression) = expression {
+                    self.fold(expression);
+                }
+            }
+            Stmt::Block { statements } => self.optimize_stmts(statements),
+            Stmt::Fn { body, .. } => self.optimize_stmts(Rc::make_mut(body)),
+            Stmt::If { condition, then_branch, else_branch } => {
+                self.fold(condition);
+                self.optimize_stmt(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.optimize_stmt(else_branch);
+                }
+
+                let truthy = match condition {
+                    Expr::Literal { value } => Some(ops::is_truthy(value)),
+                    _ => None
+                };
+                if let Some(truthy) = truthy {
+                    *stmt = if truthy {
+                        std::mem::replace(then_branch.as_mut(), empty())
+                    } else {
+                        match else_branch.take() {
+                            Some(else_branch) => *else_branch,
+                            None => empty()
+                        }
+                    };
+                }
+            }
+            Stmt::For { expression, body, .. } => {
+                self.fold(expression);
+                self.optimize_stmts(body);
+            }
+            Stmt::Expression { expression } => self.fold(expression)
+        }
+    }
+
+    fn fold(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Grouping { expression } => {
+                self.fold(expression);
+                if let Expr::Literal { .. } = expression.as_ref() {
+                    *expr = take(expression);
+                }
+            }
+            Expr::Variable { .. } => (),
+            Expr::Assign { value, .. } => self.fold(value),
+            Expr::Unary { operator, value } => {
+                self.fold(value);
+                if let Expr::Literal { value } = value.as_ref() {
+                    if let Ok(value) = ops::unary(operator, value.clone()) {
+                        *expr = Expr::Literal { value };
+                    }
+                }
+            }
+            Expr::Binary { .. } | Expr::Logical { .. } => self.fold_chain(expr),
+            Expr::Call { callee, arguments, .. } => {
+                self.fold(callee);
+                arguments.iter_mut().for_each(|argument| self.fold(argument));
+            }
+            Expr::Literal { .. } => (),
+            Expr::Array { value } => {
+                value.iter_mut().for_each(|expr| self.fold(expr));
+                if value.iter().all(|expr| matches!(expr, Expr::Literal { .. })) {
+                    let values = value.drain(..).map(|expr| match expr {
+                        Expr::Literal { value } => value,
+                        _ => unreachable!()
+                    }).collect();
+                    *expr = Expr::Literal { value: AliceObject::array(values) };
+                }
+            }
+            Expr::Index { object, index, .. } => {
+                self.fold(object);
+                self.fold(index);
+            }
+            Expr::Get { object, .. } => self.fold(object),
+            Expr::SetIndex { object, index, value, .. } => {
+                self.fold(object);
+                self.fold(index);
+                self.fold(value);
+            }
+            Expr::Range { start, dots, end } => {
+                self.fold(start);
+                self.fold(end);
+                if let (Expr::Literal { value: s }, Expr::Literal { value: e }) = (start.as_ref(), end.as_ref()) {
+                    if let Ok(value) = ops::range(s.clone(), dots, e.clone()) {
+                        *expr = Expr::Literal { value };
+                    }
+                }
+            }
+        }
+    }
+
+    /// Folds a chain of binary and logical operators from its first operand
+    /// out, taking the chain apart to reach that operand without recursing
+    /// down its left side.
+    fn fold_chain(&mut self, expr: &mut Expr) {
+        let mut links = Vec::new();
+        let mut folded = take(expr);
+        while let Expr::Binary { left, .. } | Expr::Logical { left, .. } = &mut folded {
+            let first = take(left);
+            links.push(std::mem::replace(&mut folded, first));
+        }
+
+        self.fold(&mut folded);
+        for mut link in links.into_iter().rev() {
+            if let Expr::Binary { left, .. } | Expr::Logical { left, .. } = &mut link {
+                **left = folded;
+            }
+            self.fold_link(&mut link);
+            folded = link;
+        }
+        *expr = folded;
+    }
+
+    /// Folds a binary or logical operator whose left operand is folded.
+    fn fold_link(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Binary { left, operator, right } => {
+                self.fold(right);
+                if let (Expr::Literal { value: l }, Expr::Literal { value: r }) = (left.as_ref(), right.as_ref()) {
+                    if let Ok(value) = ops::binary(l.clone(), operator, r.clone()) {
+                        *expr = Expr::Literal { value };
+                    }
+                }
+            }
+            Expr::Logical { left, operator, right } => {
+                self.fold(right);
+                if let Expr::Literal { value } = left.as_ref() {
+                    let short_circuits = if operator.r#type == TokenType::Or {
+                        ops::is_truthy(value)
+                    } else {
+                        !ops::is_truthy(value)
+                    };
+                    *expr = if short_circuits { take(left) } else { take(right) };
+                }
+            }
+            _ => self.fold(expr)
+        }
+    }
+}
+
+#[inline]
+fn take(expr: &mut Expr) -> Expr {
+    std::mem::replace(expr, Expr::Literal { value: AliceObject::Nil })
+}
+
+#[inline]
+fn empty() -> Stmt {
+    Stmt::Block { statements: Vec::new() }
+}
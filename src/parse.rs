@@ -0,0 +1,714 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{token::{Token, TokenType, Literal, Span}, error::AliceError, ast::{Stmt, Expr, AliceObject, CallSite}};
+
+/// How deeply expressions and statements may nest. Every later pass walks
+/// the AST recursively, so the limit keeps all of them within the stack.
+/// The parser itself needs the most, some 35 KB a level in a debug build;
+/// this fits a thread's default 2 MB stack.
+const MAX_DEPTH: usize = 50;
+
+/// How many binary and logical operators a statement may have. A chain of
+/// them nests, as `a + b + c` is `(a + b) + c`; passes walk a chain in a
+/// loop, but dropping one still recurses a small frame per operator.
+const MAX_OPERATORS: usize = 10_000;
+
+/// The operators that can only come between two operands, which lets a line
+/// starting with one continue the line before it.
+const BINARY_OPERATORS: &[TokenType] = &[
+    TokenType::Or, TokenType::And, TokenType::BangEqual, TokenType::EqualEqual, TokenType::Greater,
+    TokenType::GreaterEqual, TokenType::Less, TokenType::LessEqual, TokenType::Minus, TokenType::Plus,
+    TokenType::Slash, TokenType::Star, TokenType::PercentSign
+];
+
+pub struct Parser<'src> {
+    /// The text the tokens were scanned from.
+    source: &'src str,
+    tokens: Vec<Token>,
+    current: usize,
+    depth: usize,
+    /// Binary and logical operators in the statement being parsed.
+    operators: usize,
+    /// How many `(`/`[` are open; inside them line breaks never end a
+    /// statement.
+    brackets: usize,
+    /// The `;` that a line starting with an operator was last found to
+    /// continue up to.
+    continued_to: usize,
+    /// Doc comments, keyed by the index of the token that follows them.
+    docs: HashMap<usize, Box<str>>
+}
+
+impl<'src> Parser<'src> {
+    pub fn new(source: &'src str, tokens: Vec<Token>) -> Parser<'src> {
+        let (mut tokens, docs) = take_docs(tokens);
+        if tokens.last().map(|token| token.r#type) != Some(TokenType::Eof) {
+            let (line, end) = tokens.last().map_or((1, 0), |token| (token.line, token.span.end));
+            tokens.push(Token {
+                r#type: TokenType::Eof,
+                lexeme: None,
+                literal: None,
+                line,
+                column: 0,
+                span: Span { start: end, end }
+            });
+        }
+
+        Parser { source, tokens, current: 0, depth: 0, operators: 0, brackets: 0, continued_to: 0, docs }
+    }
+
+    #[inline]
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, AliceError> {
+        let mut statements = Vec::new();
+
+        while !self.is_at_end() {
+            self.depth = 0;
+            self.brackets = 0;
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(e) => return Err(e)
+            }
+        }
+
+        Ok(statements)
+    }
+
+    fn declaration(&mut self) -> Result<Stmt, AliceError> {
+        self.operators = 0;
+        let doc = self.docs.remove(&self.current);
+        if self.matches(&[TokenType::Fn]) {
+            return self.function(doc);
+        }
+        if self.matches(&[TokenType::Let]) {
+            return self.var_declaration(doc);
+        }
+
+        self.statement()
+    }
+
+    fn function(&mut self, doc: Option<Box<str>>) -> Result<Stmt, AliceError> {
+        let depth = self.depth;
+        self.enter()?;
+        let function = self.function_declaration(doc);
+        self.depth = depth;
+        function
+    }
+
+    fn function_declaration(&mut self, doc: Option<Box<str>>) -> Result<Stmt, AliceError> {
+        let name = self.consume(TokenType::Identifier, "Expect function name.")?;
+        self.consume(TokenType::LeftParen, "Expect '(' after function name.")?;
+
+        let mut params = Vec::new();
+        if !self.check(TokenType::RightParen) {
+            loop {
+                params.push(self.consume(TokenType::Identifier, "Expect parameter name.")?);
+                if !self.matches(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before function body.")?;
+        let body = self.block()?;
+
+        Ok(Stmt::Fn { name, params, body: Rc::new(body), doc })
+    }
+
+    fn var_declaration(&mut self, doc: Option<Box<str>>) -> Result<Stmt, AliceError> {
+        let name = self.consume(TokenType::Identifier, "Expect variable name.")?;
+
+        let mut initializer: Option<Expr> = None;
+
+        if self.continues(&[TokenType::Equal]) {
+            initializer = match self.expression() {
+                Ok(expr) => Some(expr),
+                Err(e) => return Err(e)
+            };
+        }
+
+        self.terminator("Expect ';' after variable declaration.")?;
+        Ok(Stmt::Var { name, initializer, doc })
+    }
+
+    fn expression(&mut self) -> Result<Expr, AliceError> {
+        let depth = self.depth;
+        self.enter()?;
+        let expr = self.assignment();
+        self.depth = depth;
+        expr
+    }
+
+    fn assignment(&mut self) -> Result<Expr, AliceError> {
+        let expr = self.or()?;
+
+        if self.continues(&[TokenType::Equal]) {
+            let equals = self.previous();
+            let depth = self.depth;
+            self.enter()?;
+            let value = self.assignment()?;
+            self.depth = depth;
+
+            match expr {
+                Expr::Variable { name, .. } => return Ok(Expr::Assign { name, value: Box::new(value), binding: None }),
+                Expr::Index { object, bracket, index } => return Ok(Expr::SetIndex { object, bracket, index, value: Box::new(value) }),
+                _ => return Err(AliceError::parse("Invalid assignment target.", &equals))
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, AliceError> {
+        let mut expr = self.and()?;
+
+        while self.continues(&[TokenType::Or]) {
+            self.link()?;
+            let operator = self.previous();
+            let right = self.and()?;
+            expr = Expr::Logical { left: Box::new(expr), operator, right: Box::new(right) }
+        }
+        
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, AliceError> {
+        let mut expr = self.equality()?;
+
+        while self.continues(&[TokenType::And]) {
+            self.link()?;
+            let operator = self.previous();
+            let right = self.equality()?;
+            expr = Expr::Logical { left: Box::new(expr), operator, right: Box::new(right) }
+        }
+        
+        Ok(expr)
+    }
+
+    fn statement(&mut self) -> Result<Stmt, AliceError> {
+        // A statement's expressions come before any statement nested in it,
+        // and one statement's expressions never nest in another's.
+        self.operators = 0;
+        let depth = self.depth;
+        self.enter()?;
+        let stmt = self.nested_statement();
+        self.depth = depth;
+        stmt
+    }
+
+    fn nested_statement(&mut self) -> Result<Stmt, AliceError> {
+        if self.matches(&[TokenType::If]) {
+            self.if_statement()
+        }
+        else if self.matches(&[TokenType::For]) {
+            self.for_statement()
+        }
+        else if self.matches(&[TokenType::Return]) {
+            self.return_statement()
+        }
+        else if self.matches(&[TokenType::LeftBrace]) {
+            let statements = self.block()?;
+            Ok(Stmt::Block { statements })
+        }
+        else {
+            self.expression_statement()
+        }
+    }
+
+    fn if_statement(&mut self) -> Result<Stmt, AliceError> {
+        let condition = self.expression()?;
+
+        let then_branch = Box::new(self.statement()?);
+        
+        let mut else_branch: Option<Box<Stmt>> = None;
+        if self.matches(&[TokenType::Else]) {
+            else_branch = match self.statement() {
+                Ok(stmt) => Some(Box::new(stmt)),
+                Err(e) => return Err(e)
+            };
+        }
+
+        Ok(Stmt::If { condition, then_branch, else_branch })
+    }
+
+    fn for_statement(&mut self) -> Result<Stmt, AliceError> {
+        let value = if let Ok(Expr::Variable { name, .. }) = self.expression() {
+            name
+        } else {
+            return Err(AliceError::parse("Expect variable name.", &self.peek()))
+        };
+
+        self.consume(TokenType::In, "Expext 'in' after variable.")?;
+
+        let expression = self.expression()?;
+
+        if self.matches(&[TokenType::LeftBrace]) {
+            let body = self.block()?;
+            Ok(Stmt::For { value, expression, body }) 
+        } else {
+            Err(AliceError::parse("Expect '{'.", &self.peek()))
+        }
+    }
+
+    fn block(&mut self) -> Result<Vec<Stmt>, AliceError> {
+        let mut statements = Vec::new();
+
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            statements.push(self.declaration()?);
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
+
+        Ok(statements)
+    }
+
+    fn return_statement(&mut self) -> Result<Stmt, AliceError> {
+        let keyword = self.previous();
+
+        let mut value = None;
+        if !self.at_terminator() {
+            value = Some(self.expression()?);
+        }
+
+        self.terminator("Expect ';' after return value.")?;
+        Ok(Stmt::Return { keyword, value })
+    }
+
+    fn expression_statement(&mut self) -> Result<Stmt, AliceError> {
+        let expr = self.expression()?;
+        self.terminator("Expect ';' after expression.")?;
+        Ok(Stmt::Expression { expression: expr })
+    }
+
+    fn equality(&mut self) -> Result<Expr, AliceError> {
+        let mut expr = self.comparison()?;
+
+        while self.continues(&[TokenType::BangEqual, TokenType::EqualEqual]) {
+            self.link()?;
+            let operator = self.previous();
+            let right = self.comparison()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right)
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, AliceError> {
+        let mut expr = self.term()?;
+
+        while self.continues(&[
+            TokenType::Greater,
+            TokenType::GreaterEqual,
+            TokenType::Less,
+            TokenType::LessEqual
+        ]) {
+            self.link()?;
+            let operator = self.previous();
+            let right = self.term()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right)
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn term(&mut self) -> Result<Expr, AliceError> {
+        let mut expr = self.factor()?;
+
+        while self.continues(&[TokenType::Minus, TokenType::Plus]) {
+            self.link()?;
+            let operator = self.previous();
+            let right = self.factor()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right)
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn factor(&mut self) -> Result<Expr, AliceError> {
+        let mut expr = self.unary()?;
+
+        while self.continues(&[TokenType::Slash, TokenType::Star, TokenType::PercentSign]) {
+            self.link()?;
+            let operator = self.previous();
+            let right = self.unary()?;
+            expr = Expr::Binary {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right)
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, AliceError> {
+        if self.matches(&[TokenType::Bang, TokenType::Minus]) {
+            let operator = self.previous();
+            let depth = self.depth;
+            self.enter()?;
+            let right = self.unary()?;
+            self.depth = depth;
+            return Ok(Expr::Unary {
+                operator,
+                value: Box::new(right)
+            });
+        }
+
+        self.call()
+    }
+
+    fn call(&mut self) -> Result<Expr, AliceError> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.continues(&[TokenType::LeftParen]) {
+                self.enter()?;
+                expr = self.bracketed(|parser| parser.finish_call(expr))?;
+            } else if self.continues(&[TokenType::LeftSquare]) {
+                self.enter()?;
+                let index = self.bracketed(Parser::expression)?;
+                let bracket = self.consume(TokenType::RightSquare, "Expect ']' after index.")?;
+                expr = Expr::Index { object: Box::new(expr), bracket, index: Box::new(index) };
+            } else if self.check_next(TokenType::Identifier) && self.continues(&[TokenType::Dot]) {
+                // Checking for the name first keeps `[a..b]` a range.
+                self.enter()?;
+                let name = self.consume(TokenType::Identifier, "Expect member name after '.'.")?;
+                expr = Expr::Get { object: Box::new(expr), name };
+            } else {
+                break;
+            }
+        }
+
+        Ok(expr)
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, AliceError> {
+        let mut arguments = Vec::new();
+        let mut keywords: Vec<Token> = Vec::new();
+        // `dbg` shows the text of its arguments along with their values.
+        let dbg = matches!(&callee, Expr::Variable { name, .. } if name.symbol().as_str() == "dbg");
+        let mut source = Vec::new();
+
+        if !self.check(TokenType::RightParen) {
+            loop {
+                if self.check(TokenType::Identifier) && self.check_next(TokenType::Colon) {
+                    let name = self.advance();
+                    if keywords.iter().any(|keyword| keyword.lexeme == name.lexeme) {
+                        return Err(AliceError::parse(format!("Keyword argument '{}' is given twice.", name.symbol()), &name));
+                    }
+                    self.advance();
+                    keywords.push(name);
+                } else if let Some(keyword) = keywords.last() {
+                    return Err(AliceError::parse("Expect keyword arguments after positional ones.", &self.peek())
+                        .with_note(format!("'{}' is a keyword argument.", keyword.symbol())));
+                }
+                let start = self.peek().span.start;
+                arguments.push(self.expression()?);
+                if dbg {
+                    source.push(self.source.get(start..self.previous().span.end).unwrap_or_default().into());
+                }
+                if !self.matches(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+
+        let paren = self.consume(TokenType::RightParen, "Expect ')' after arguments.")?;
+
+        Ok(Expr::Call { callee: Box::new(callee), site: Rc::new(CallSite { paren, keywords, source }), arguments })
+    }
+
+    fn primary(&mut self) -> Result<Expr, AliceError> {
+        if self.matches(&[TokenType::False]) {
+            return Ok(Expr::Literal {
+                value: AliceObject::Boolean(false)
+            });
+        }
+        if self.matches(&[TokenType::True]) {
+            return Ok(Expr::Literal {
+                value: AliceObject::Boolean(true)
+            });
+        }
+        if self.matches(&[TokenType::Nil]) {
+            return Ok(Expr::Literal {
+                value: AliceObject::Nil
+            });
+        }
+
+        if self.matches(&[TokenType::Identifier]) {
+            return Ok(Expr::Variable { 
+                name: self.previous(),
+                binding: None
+            });
+        }
+
+        if self.matches(&[TokenType::String, TokenType::F64, TokenType::I64]) {
+            match self.previous().literal {
+                Some(Literal::String(str)) =>  return Ok(Expr::Literal { value: AliceObject::String(str) }),
+                Some(Literal::F64(num)) =>        return Ok(Expr::Literal { value: AliceObject::F64(num) }),
+                Some(Literal::I64(num)) =>        return Ok(Expr::Literal { value: AliceObject::I64(num) }),
+                _ => return Err(AliceError::parse("Expect a literal value.", &self.previous()))
+            }
+        }
+
+        if self.matches(&[TokenType::LeftParen]) {
+            let expr = self.bracketed(Parser::expression)?;
+            self.consume(TokenType::RightParen, "Expect ')' after expression.")?;
+            return Ok(Expr::Grouping {
+                expression: Box::new(expr)
+            });
+        }
+
+        if self.matches(&[TokenType::LeftSquare]) {
+            return self.bracketed(Parser::array);
+        }
+
+        Err(AliceError::parse("Expect expression.", &self.peek()))
+    }
+
+    /// The rest of an array literal or range after its `[`.
+    fn array(&mut self) -> Result<Expr, AliceError> {
+        let mut list = Vec::new();
+
+        if self.matches(&[TokenType::RightSquare]) {
+            return Ok(Expr::Array { value: list });
+        }
+
+        while !self.matches(&[TokenType::RightSquare]) {
+            let expr = self.expression()?;
+
+            if self.peek().r#type == TokenType::Dot {
+                let mut dots = self.advance();
+                if self.peek().r#type == TokenType::Dot {
+                    dots.span.end = self.advance().span.end;
+                    let end = self.expression()?;
+                    self.consume(TokenType::RightSquare, "Expect ']' after range.")?;
+                    return Ok(Expr::Range { start: Box::new(expr), dots, end: Box::new(end) });
+                }
+            }
+
+            if let Err(e) = self.consume(TokenType::Comma, "Expect ',' after expression.") {
+                if self.peek().r#type != TokenType::RightSquare {
+                    return Err(e);
+                }
+            };
+            
+            list.push(expr);
+        }
+
+        Ok(Expr::Array { value: list })
+    }
+}
+
+impl Parser<'_> {
+    /// Enters one more level of nesting. Besides nested expressions and
+    /// statements, every call, index and member access counts, since
+    /// `f(a)[0]` nests as `(f(a))[0]`. `expression` and `statement` restore
+    /// the depth once their part is parsed.
+    fn enter(&mut self) -> Result<(), AliceError> {
+        if self.depth >= MAX_DEPTH {
+            let msg = format!("Code is nested too deeply (more than {} levels).", MAX_DEPTH);
+            return Err(AliceError::parse(msg, &self.peek())
+                .with_note("brackets, blocks, unary operators and each call or index in a chain add a level")
+                .with_suggestion("split it up using intermediate variables or functions"));
+        }
+
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Counts one more binary or logical operator in the statement. These
+    /// don't add a level of nesting: a chain of them is flat in the source.
+    fn link(&mut self) -> Result<(), AliceError> {
+        if self.operators >= MAX_OPERATORS {
+            let msg = format!("Statement has too many operators (more than {}).", MAX_OPERATORS);
+            return Err(AliceError::parse(msg, &self.peek())
+                .with_suggestion("split it up using intermediate variables"));
+        }
+
+        self.operators += 1;
+        Ok(())
+    }
+
+    /// Parses `f` with line breaks ignored, as inside brackets.
+    fn bracketed<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, AliceError>) -> Result<T, AliceError> {
+        self.brackets += 1;
+        let result = f(self);
+        self.brackets -= 1;
+        result
+    }
+
+    /// Like `matches`, for a token that would continue the current
+    /// expression. Outside brackets a line break ends the expression, so
+    /// such a token only continues it if it is on the same line; a line
+    /// ending in an operator or open bracket continues on its own, and so
+    /// does one starting with a binary operator in a statement that ends in
+    /// an explicit `;`.
+    #[inline]
+    fn continues(&mut self, types: &[TokenType]) -> bool {
+        if self.brackets == 0 && self.on_new_line() && !self.continues_line() {
+            return false;
+        }
+        self.matches(types)
+    }
+
+    /// Whether the next token, which starts a new line, is a binary operator
+    /// continuing the line before. It is if the statement goes on to end
+    /// with a `;`, with every line break before it inside brackets, before
+    /// a binary operator or after one.
+    fn continues_line(&mut self) -> bool {
+        if !BINARY_OPERATORS.contains(&self.peek().r#type) {
+            return false;
+        }
+        if self.current < self.continued_to {
+            return true;
+        }
+
+        let mut brackets = 0usize;
+        for i in self.current..self.tokens.len() {
+            let token = &self.tokens[i];
+            match token.r#type {
+                TokenType::LeftParen | TokenType::LeftSquare | TokenType::LeftBrace => brackets += 1,
+                TokenType::RightParen | TokenType::RightSquare | TokenType::RightBrace if brackets == 0 => return false,
+                TokenType::RightParen | TokenType::RightSquare | TokenType::RightBrace => brackets -= 1,
+                TokenType::Semicolon if brackets == 0 => {
+                    self.continued_to = i;
+                    return true;
+                }
+                TokenType::Eof => return false,
+                r#type if brackets == 0 && i > self.current && token.line > self.end_line(&self.tokens[i - 1])
+                    && !BINARY_OPERATORS.contains(&r#type) && !BINARY_OPERATORS.contains(&self.tokens[i - 1].r#type) => return false,
+                _ => ()
+            }
+        }
+        false
+    }
+
+    /// Ends a statement: a `;`, or else a line break, a `}` or the end of
+    /// input after it.
+    fn terminator(&mut self, msg: &str) -> Result<(), AliceError> {
+        if self.matches(&[TokenType::Semicolon]) || self.at_terminator() {
+            return Ok(());
+        }
+        self.consume(TokenType::Semicolon, msg).map(|_| ())
+    }
+
+    #[inline]
+    fn at_terminator(&self) -> bool {
+        self.check(TokenType::Semicolon) || self.check(TokenType::RightBrace) || self.is_at_end() || self.on_new_line()
+    }
+
+    /// Whether the next token starts a new line, after the one the previous
+    /// token ends on.
+    #[inline]
+    fn on_new_line(&self) -> bool {
+        self.current > 0 && self.tokens[self.current].line > self.end_line(&self.tokens[self.current - 1])
+    }
+
+    /// The line `token` ends on, which is a later one than it starts on for
+    /// a string with line breaks in it.
+    #[inline]
+    fn end_line(&self, token: &Token) -> u32 {
+        let text = self.source.get(token.span.start..token.span.end).unwrap_or_default();
+        token.line + text.matches('\n').count() as u32
+    }
+
+    #[inline]
+    fn matches(&mut self, types: &[TokenType]) -> bool {
+        for &t in types {
+            if self.check(t) {
+                self.advance();
+                return true;
+            }
+        }
+
+        false
+    }
+
+    #[inline]
+    fn check(&self, t: TokenType) -> bool {
+        if self.is_at_end() {
+            return false;
+        }
+        self.peek().r#type == t
+    }
+
+    /// Whether the token after the current one has type `t`.
+    fn check_next(&self, t: TokenType) -> bool {
+        self.tokens.get(self.current + 1).is_some_and(|token| token.r#type == t)
+    }
+
+    #[inline]
+    fn previous(&self) -> Token {
+        self.tokens[self.current - 1].clone()
+    }
+
+    #[inline]
+    fn is_at_end(&self) -> bool {
+        self.peek().r#type == TokenType::Eof
+    }
+
+    #[inline]
+    fn peek(&self) -> Token {
+        self.tokens[self.current].clone()
+    }
+
+    #[inline]
+    fn advance(&mut self) -> Token {
+        if !self.is_at_end() {
+            self.current += 1;
+        }
+        self.previous()
+    }
+
+    #[inline]
+    fn consume(&mut self, t: TokenType, msg: &str) -> Result<Token, AliceError> {
+        if self.check(t) {
+            return Ok(self.advance());
+        }
+
+        let error = AliceError::parse(msg, &self.peek());
+        if t == TokenType::Semicolon {
+            // Point at the end of the statement rather than at the next token.
+            return Err(error.at(&self.previous()).with_suggestion("add ';' or a line break at the end of the statement"));
+        }
+        Err(error)
+    }
+}
+
+/// Moves doc comments out of the token stream, so they are allowed
+/// anywhere, joining consecutive lines into one comment.
+fn take_docs(tokens: Vec<Token>) -> (Vec<Token>, HashMap<usize, Box<str>>) {
+    let mut rest = Vec::with_capacity(tokens.len());
+    let mut docs = HashMap::new();
+    let mut lines: Vec<String> = Vec::new();
+
+    for token in tokens {
+        if token.r#type == TokenType::DocComment {
+            if let Some(Literal::String(line)) = token.literal {
+                lines.push(line.to_string());
+            }
+            continue;
+        }
+
+        if !lines.is_empty() {
+            docs.insert(rest.len(), lines.join("\n").into());
+            lines.clear();
+        }
+        rest.push(token);
+    }
+
+    (rest, docs)
+}
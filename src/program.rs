@@ -0,0 +1,62 @@
+use std::fmt::Debug;
+
+use crate::{ast::Stmt, error::AliceError, parse::Parser, resolver::Resolver, token::Token};
+
+/// A parsed script, ready for `Engine::run`.
+pub struct Program {
+    pub(crate) statements: Vec<Stmt>
+}
+
+impl Program {
+    /// Parses the tokens the `Scanner` produced from `source`.
+    pub fn parse(source: &str, tokens: Vec<Token>) -> Result<Program, AliceError> {
+        Parser::new(source, tokens).parse().map(|statements| Program { statements })
+    }
+
+    /// The program's statements, as parsed.
+    #[inline]
+    pub fn statements(&self) -> &[Stmt] {
+        &self.statements
+    }
+
+    /// The `///` comments written above the program's `fn` and `let`
+    /// declarations, with the names they document, in source order.
+    /// Declarations inside functions and blocks are included.
+    pub fn docs(&self) -> Vec<(&str, &str)> {
+        fn collect<'a>(statements: &'a [Stmt], docs: &mut Vec<(&'a str, &'a str)>) {
+            for stmt in statements {
+                if let (Stmt::Var { name, .. } | Stmt::Fn { name, .. }, Some(doc)) = (stmt, stmt.doc()) {
+                    docs.push((name.name(), doc));
+                }
+                match stmt {
+                    Stmt::Fn { body: statements, .. } => collect(statements, docs),
+                    Stmt::Block { statements } | Stmt::For { body: statements, .. } => collect(statements, docs),
+                    Stmt::If { then_branch, else_branch, .. } => {
+                        collect(std::slice::from_ref(then_branch), docs);
+                        if let Some(else_branch) = else_branch {
+                            collect(std::slice::from_ref(else_branch), docs);
+                        }
+                    }
+                    _ => ()
+                }
+            }
+        }
+
+        let mut docs = Vec::new();
+        collect(&self.statements, &mut docs);
+        docs
+    }
+
+    /// Reports the errors a fresh engine would find in the program before
+    /// running any of it, such as a variable read in its own initializer.
+    pub fn check(&mut self) -> Result<(), AliceError> {
+        Resolver::new().resolve(&mut self.statements)
+    }
+}
+
+impl Debug for Program {
+    /// Writes the syntax tree, a statement per line.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.statements.iter().try_for_each(|stmt| writeln!(f, "{:?}", stmt))
+    }
+}
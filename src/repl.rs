@@ -0,0 +1,186 @@
+use alice::{Engine as _, Interpreter, Program, Vm, scanner::{self, Scanner}, error::{AliceError, ErrorKind}};
+
+use crate::{Engine, MAX_CALL_DEPTH, Options, report, editor::{Editor, Input}};
+
+const HELP: &str = "\
+Enter statements or expressions; the value of a bare expression is echoed.
+Input continues on the next line while brackets or strings are left open.
+
+:help          show this message
+:env           list the global variables
+:load <file>   run a file in this session
+:reset         forget every definition
+:quit          leave (or press Ctrl-D)";
+
+/// An interactive session. Definitions persist from one input to the next
+/// until `:reset`.
+pub struct Repl {
+    options: Options,
+    editor: Editor,
+    runtime: Runtime
+}
+
+enum Runtime {
+    Tree(Interpreter),
+    Vm(Vm)
+}
+
+impl Repl {
+    pub fn new(options: Options) -> Repl {
+        Repl { options, editor: Editor::new(), runtime: Runtime::new(options) }
+    }
+
+    /// Runs the session until the input ends, `:quit` or a call to `exit`,
+    /// returning the exit status.
+    pub fn run(&mut self) -> std::io::Result<i32> {
+        let mut buffer = String::new();
+
+        loop {
+            let prompt = if buffer.is_empty() { "> " } else { "... " };
+            let runtime = &self.runtime;
+            let line = match self.editor.read_line(prompt, || runtime.completions())? {
+                Input::Line(line) => line,
+                Input::Interrupted => {
+                    buffer.clear();
+                    continue;
+                }
+                Input::Eof => {
+                    println!();
+                    return Ok(0);
+                }
+            };
+
+            let line = match scanner::decode(&line) {
+                Ok(line) => line,
+                Err(e) => {
+                    report(e, None, self.options.format);
+                    buffer.clear();
+                    continue;
+                }
+            };
+
+            if buffer.is_empty() {
+                if let Some(command) = line.trim().strip_prefix(':') {
+                    match self.command(command) {
+                        None => continue,
+                        Some(status) => return Ok(status)
+                    }
+                }
+            }
+
+            buffer.push_str(line);
+            if scanner::is_incomplete(&buffer) {
+                continue;
+            }
+
+            match self.eval(&buffer, None) {
+                Err(AliceError { kind: ErrorKind::Exit(status), .. }) => return Ok(status),
+                Err(e) => report(e, None, self.options.format),
+                Ok(()) => ()
+            }
+            buffer.clear();
+        }
+    }
+
+    /// Runs a meta-command, returning the exit status if the session should
+    /// end.
+    fn command(&mut self, command: &str) -> Option<i32> {
+        let (name, argument) = match command.split_once(char::is_whitespace) {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (command, "")
+        };
+
+        match name {
+            "help" | "h" => println!("{HELP}"),
+            "env" => {
+                let globals = match &self.runtime {
+                    Runtime::Tree(interpreter) => interpreter.globals(),
+                    Runtime::Vm(vm) => vm.globals()
+                };
+                globals.into_iter().for_each(|(name, value)| println!("{name} = {value}"));
+            }
+            "load" if argument.is_empty() => println!("usage: :load <file>"),
+            "load" => return self.load(argument),
+            "reset" => self.runtime = Runtime::new(self.options),
+            "quit" | "q" | "exit" => return Some(0),
+            other => println!("unknown command ':{other}', type :help for a list.")
+        }
+        None
+    }
+
+    /// Runs a file in the session, returning the exit status if it called
+    /// `exit`.
+    fn load(&mut self, path: &str) -> Option<i32> {
+        let source = match std::fs::read(path) {
+            Ok(source) => source,
+            Err(e) => {
+                println!("can't read '{path}': {e}.");
+                return None;
+            }
+        };
+
+        match scanner::decode(&source).and_then(|source| self.eval(source, Some(path))) {
+            Err(AliceError { kind: ErrorKind::Exit(status), .. }) => return Some(status),
+            Err(e) => report(e, Some(path), self.options.format),
+            Ok(()) => ()
+        }
+        None
+    }
+
+    /// Scans, parses and runs `source` in the session. Typed input (no
+    /// `file`) echoes the value of a trailing bare expression.
+    fn eval(&mut self, source: &str, file: Option<&str>) -> Result<(), AliceError> {
+        let echo = file.is_none();
+
+        let tokens = match Scanner::new(source).scan_tokens() {
+            Ok(tokens) => tokens,
+            Err(mut errors) => {
+                let last = errors.pop().expect("a failed scan has at least one error");
+                errors.into_iter().for_each(|e| report(e, file, self.options.format));
+                return Err(last);
+            }
+        };
+
+        let program = Program::parse(source, tokens)?;
+        let value = match &mut self.runtime {
+            Runtime::Tree(interpreter) if echo => interpreter.run_line(program)?,
+            Runtime::Tree(interpreter) => interpreter.run(program).map(|_| None)?,
+            Runtime::Vm(vm) if echo => vm.run_line(program)?,
+            Runtime::Vm(vm) => vm.run(program).map(|_| None)?
+        };
+
+        if let Some(value) = value {
+            println!("{value}");
+        }
+        Ok(())
+    }
+}
+
+impl Runtime {
+    fn new(options: Options) -> Runtime {
+        match options.engine {
+            Engine::Tree => {
+                let mut interpreter = Interpreter::new();
+                interpreter.set_optimize(options.optimize);
+                interpreter.set_max_call_depth(MAX_CALL_DEPTH);
+                Runtime::Tree(interpreter)
+            }
+            Engine::Vm => {
+                let mut vm = Vm::new();
+                vm.set_optimize(options.optimize);
+                vm.set_max_call_depth(MAX_CALL_DEPTH);
+                Runtime::Vm(vm)
+            }
+        }
+    }
+
+    /// Words offered by tab completion: the keywords and every global.
+    fn completions(&self) -> Vec<String> {
+        let mut words: Vec<String> = scanner::keywords().map(str::to_owned).collect();
+        match self {
+            Runtime::Tree(interpreter) => words.extend(interpreter.global_names().map(str::to_owned)),
+            Runtime::Vm(vm) => words.extend(vm.global_names().map(str::to_owned))
+        }
+        words
+    }
+}
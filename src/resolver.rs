@@ -0,0 +1,257 @@
+use std::{collections::HashSet, rc::Rc};
+
+use crate::{ast::{Stmt, Expr, Binding}, error::AliceError, symbol::Symbol, token::Token};
+
+/// Static pass run between `Parser::parse` and `Interpreter::interpret`.
+///
+/// Every `Expr::Variable`/`Expr::Assign` that refers to a local gets the
+/// `Binding` of the environment slot it lives in, so the interpreter never
+/// looks locals up by name. Names that are not local are left unbound and
+/// resolved as globals at runtime.
+#[derive(Default)]
+pub struct Resolver {
+    scopes: Vec<Scope>,
+    globals: HashSet<Symbol>,
+    pending_globals: HashSet<Symbol>,
+    initializing_global: Option<Symbol>,
+    function_base: Option<usize>
+}
+
+#[derive(Default)]
+struct Scope {
+    names: Vec<(Symbol, bool)>,
+    pending: HashSet<Symbol>
+}
+
+impl Resolver {
+    #[inline]
+    pub fn new() -> Resolver {
+        Resolver::default()
+    }
+
+    /// Resolves a whole program. Top-level declarations are remembered, so
+    /// one resolver can be reused for consecutive chunks of a session.
+    pub fn resolve(&mut self, statements: &mut [Stmt]) -> Result<(), AliceError> {
+        self.pending_globals = declared_names(statements);
+
+        let result = self.resolve_stmts(statements);
+
+        self.pending_globals.clear();
+        self.initializing_global = None;
+        self.scopes.clear();
+        self.function_base = None;
+
+        result
+    }
+
+    fn resolve_stmts(&mut self, statements: &mut [Stmt]) -> Result<(), AliceError> {
+        for stmt in statements {
+            self.resolve_stmt(stmt)?;
+        }
+        Ok(())
+    }
+
+    fn resolve_stmt(&mut self, stmt: &mut Stmt) -> Result<(), AliceError> {
+        match stmt {
+            Stmt::Return { keyword, value } => {
+                if self.function_base.is_none() {
+                    return Err(AliceError::parse("Can't return from top-level code.", keyword));
+                }
+                if let Some(value) = value {
+                    self.resolve_expr(value)?;
+                }
+            }
+            Stmt::Var { name, initializer, .. } => {
+                let lexeme = lexeme(name);
+                if self.scopes.is_empty() {
+                    self.initializing_global = Some(lexeme.clone());
+                    if let Some(initializer) = initializer {
+                        self.resolve_expr(initializer)?;
+                    }
+                    self.initializing_global = None;
+                    self.globals.insert(lexeme);
+                } else {
+                    self.declare(lexeme.clone(), false);
+                    if let Some(initializer) = initializer {
+                        self.resolve_expr(initializer)?;
+                    }
+                    self.define(&lexeme);
+                }
+            }
+            Stmt::Block { statements } => {
+                self.begin_scope(statements);
+                let result = self.resolve_stmts(statements);
+                self.end_scope();
+                result?;
+            }
+            Stmt::Fn { name, params, body, .. } => {
+                let lexeme = lexeme(name);
+                if self.scopes.is_empty() {
+                    self.globals.insert(lexeme);
+                } else {
+                    self.declare(lexeme, true);
+                }
+                self.resolve_function(params, Rc::make_mut(body).as_mut_slice())?;
+            }
+            Stmt::If { condition, then_branch, else_branch } => {
+                self.resolve_expr(condition)?;
+                self.resolve_stmt(then_branch)?;
+                if let Some(else_branch) = else_branch {
+                    self.resolve_stmt(else_branch)?;
+                }
+            }
+            Stmt::For { value, expression, body } => {
+                self.resolve_expr(expression)?;
+                self.begin_scope(body);
+                self.declare(lexeme(value), true);
+                let result = self.resolve_stmts(body);
+                self.end_scope();
+                result?;
+            }
+            Stmt::Expression { expression } => {
+                self.resolve_expr(expression)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve_function(&mut self, params: &[Token], body: &mut [Stmt]) -> Result<(), AliceError> {
+        let enclosing = self.function_base.replace(self.scopes.len());
+
+        self.begin_scope(body);
+        for param in params {
+            self.declare(lexeme(param), true);
+        }
+        let result = self.resolve_stmts(body);
+        self.end_scope();
+
+        self.function_base = enclosing;
+        result
+    }
+
+    fn resolve_expr(&mut self, expr: &mut Expr) -> Result<(), AliceError> {
+        match expr {
+            Expr::Grouping { expression } => self.resolve_expr(expression),
+            Expr::Variable { name, binding } => {
+                *binding = self.resolve_local(name)?;
+                Ok(())
+            }
+            Expr::Assign { name, value, binding } => {
+                self.resolve_expr(value)?;
+                *binding = self.resolve_local(name)?;
+                Ok(())
+            }
+            Expr::Unary { value, .. } => self.resolve_expr(value),
+            Expr::Binary { .. } | Expr::Logical { .. } => {
+                let (first, rights) = expr.chain_mut();
+                self.resolve_expr(first)?;
+                for right in rights {
+                    self.resolve_expr(right)?;
+                }
+                Ok(())
+            }
+            Expr::Call { callee, arguments, .. } => {
+                self.resolve_expr(callee)?;
+                for argument in arguments {
+                    self.resolve_expr(argument)?;
+                }
+                Ok(())
+            }
+            Expr::Literal { .. } => Ok(()),
+            Expr::Array { value } => {
+                for expr in value {
+                    self.resolve_expr(expr)?;
+                }
+                Ok(())
+            }
+            Expr::Index { object, index, .. } |
+            Expr::Range { start: object, end: index, .. } => {
+                self.resolve_expr(object)?;
+                self.resolve_expr(index)
+            }
+            Expr::Get { object, .. } => self.resolve_expr(object),
+            Expr::SetIndex { object, index, value, .. } => {
+                self.resolve_expr(object)?;
+                self.resolve_expr(index)?;
+                self.resolve_expr(value)
+            }
+        }
+    }
+
+    fn resolve_local(&self, name: &Token) -> Result<Option<Binding>, AliceError> {
+        let lexeme = lexeme(name);
+
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(slot) = scope.names.iter().rposition(|(n, _)| *n == lexeme) {
+                if !scope.names[slot].1 {
+                    let msg = format!("Can't read local variable '{}' in its own initializer.", lexeme);
+                    return Err(AliceError::parse(msg, name));
+                }
+                return Ok(Some(Binding { depth, slot }));
+            }
+        }
+
+        if self.globals.contains(&lexeme) {
+            return Ok(None);
+        }
+
+        // Only code that runs straight away can be checked for ordering; a
+        // function body may legitimately mention names declared after it.
+        let base = self.function_base.unwrap_or(0);
+        let declared_later = self.scopes[base..].iter().any(|scope| scope.pending.contains(&lexeme)) ||
+            (self.function_base.is_none() && self.pending_globals.contains(&lexeme));
+
+        if self.function_base.is_none() && self.initializing_global.as_ref() == Some(&lexeme) {
+            let msg = format!("Can't read variable '{}' in its own initializer.", lexeme);
+            return Err(AliceError::parse(msg, name));
+        }
+        if declared_later {
+            let msg = format!("Can't use variable '{}' before its declaration.", lexeme);
+            return Err(AliceError::parse(msg, name)
+                .with_suggestion(format!("move the declaration of '{}' above its first use", lexeme)));
+        }
+
+        Ok(None)
+    }
+
+    #[inline]
+    fn begin_scope(&mut self, statements: &[Stmt]) {
+        self.scopes.push(Scope { names: Vec::new(), pending: declared_names(statements) });
+    }
+
+    #[inline]
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    #[inline]
+    fn declare(&mut self, name: Symbol, ready: bool) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.names.push((name, ready));
+        }
+    }
+
+    #[inline]
+    fn define(&mut self, name: &Symbol) {
+        if let Some(scope) = self.scopes.last_mut() {
+            if let Some(entry) = scope.names.iter_mut().rev().find(|(n, _)| n == name) {
+                entry.1 = true;
+            }
+        }
+    }
+}
+
+/// Names declared directly in `statements`, in any order.
+fn declared_names(statements: &[Stmt]) -> HashSet<Symbol> {
+    statements.iter().filter_map(|stmt| match stmt {
+        Stmt::Var { name, .. } |
+        Stmt::Fn { name, .. } => Some(lexeme(name)),
+        _ => None
+    }).collect()
+}
+
+#[inline]
+fn lexeme(name: &Token) -> Symbol {
+    name.symbol()
+}
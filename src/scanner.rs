@@ -1,156 +1,356 @@
-use crate::{token::{Token, TokenType, Literal}, error::AliceError};
+use crate::{token::{Token, TokenType, Literal, Span}, error::AliceError, string::AliceString, symbol::Symbol, unicode};
+
+/// Checks that raw source bytes are UTF-8, pointing at the first invalid
+/// byte otherwise.
+pub fn decode(source: &[u8]) -> Result<&str, AliceError> {
+    std::str::from_utf8(source).map_err(|e| {
+        let valid = std::str::from_utf8(&source[..e.valid_up_to()]).unwrap_or_default();
+        let line = valid.matches('\n').count() as u32 + 1;
+        let line_start = valid.rfind('\n').map_or(0, |i| i + 1);
+        let end = e.valid_up_to() + e.error_len().unwrap_or(source.len() - e.valid_up_to());
+        let span = Span { start: e.valid_up_to(), end };
+
+        AliceError::syntax("invalid UTF-8 in source.", line, column(&valid[line_start..]), span)
+            .with_note("source files must be encoded as UTF-8")
+    })
+}
+
+/// The 1-based column just after `line`, counted in characters.
+#[inline]
+fn column(line: &str) -> u32 {
+    line.chars().count() as u32 + 1
+}
+
+/// Whether `source` stops in the middle of something: an unclosed bracket,
+/// string or block comment, or a last line ending in an operator. The REPL
+/// keeps reading lines until the input is complete.
+pub fn is_incomplete(source: &str) -> bool {
+    let mut scanner = Scanner::new(source);
+    let mut open = 0i64;
+    let mut last = TokenType::Eof;
+
+    while let Some(token) = scanner.next() {
+        let token = match token {
+            Ok(token) => token,
+            Err(_) if scanner.unterminated => return true,
+            Err(_) => continue
+        };
+
+        match token.r#type {
+            TokenType::LeftParen | TokenType::LeftSquare | TokenType::LeftBrace => open += 1,
+            TokenType::RightParen | TokenType::RightSquare | TokenType::RightBrace => open -= 1,
+            TokenType::Eof | TokenType::DocComment => continue,
+            _ => ()
+        }
+        last = token.r#type;
+    }
 
-pub struct Scanner {
-    source: Vec<u8>,
+    open > 0 || matches!(last,
+        TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Slash | TokenType::PercentSign |
+        TokenType::Equal | TokenType::EqualEqual | TokenType::BangEqual | TokenType::Bang |
+        TokenType::Less | TokenType::LessEqual | TokenType::Greater | TokenType::GreaterEqual |
+        TokenType::And | TokenType::Or | TokenType::Comma | TokenType::Dot | TokenType::Colon | TokenType::Arrowhead)
+}
+
+const KEYWORDS: [(&str, TokenType); 12] = [
+    ("and",     TokenType::And),
+    ("or",      TokenType::Or),
+    ("if",      TokenType::If),
+    ("else",    TokenType::Else),
+    ("true",    TokenType::True),
+    ("false",   TokenType::False),
+    ("fn",      TokenType::Fn),
+    ("let",     TokenType::Let),
+    ("nil",     TokenType::Nil),
+    ("return",  TokenType::Return),
+    ("for",     TokenType::For),
+    ("in",      TokenType::In)
+];
+
+/// The reserved words of the language.
+pub fn keywords() -> impl Iterator<Item = &'static str> {
+    KEYWORDS.iter().map(|(keyword, _)| *keyword)
+}
+
+/// Splits source text into tokens.
+///
+/// The scanner borrows the source and produces tokens lazily as an
+/// `Iterator`; a token records the `Span` it covers instead of a copy of its
+/// text. The last item is always the `Eof` token, even after errors.
+///
+/// Lines and columns count characters, spans count bytes. A leading byte
+/// order mark is skipped.
+pub struct Scanner<'src> {
+    source: &'src str,
+    start: usize,
     current: usize,
-    line: u32
+    line: u32,
+    line_start: usize,
+    /// The column at byte `column_offset` of the current line, carried
+    /// forward from token to token so long lines aren't recounted.
+    column: u32,
+    column_offset: usize,
+    start_line: u32,
+    start_column: u32,
+    finished: bool,
+    /// Set when the input ended inside a string or block comment.
+    unterminated: bool
 }
 
-impl Scanner {
+impl<'src> Scanner<'src> {
     #[inline]
-    pub fn new(source: Vec<u8>) -> Scanner {
-        Scanner { source, current: 0, line: 1 }
+    pub fn new(source: &'src str) -> Scanner<'src> {
+        let bom = if source.starts_with('\u{feff}') { '\u{feff}'.len_utf8() } else { 0 };
+        // A `#!` line lets scripts be run directly; it ends before the line
+        // break so line numbers stay right.
+        let start = match source[bom..].starts_with("#!") {
+            true => source[bom..].find('\n').map_or(source.len(), |end| bom + end),
+            false => bom
+        };
+        Scanner { source, start, current: start, line: 1, line_start: bom, column: 1, column_offset: bom, start_line: 1, start_column: 1, finished: false, unterminated: false }
     }
 
+    /// Scans the whole source, collecting every error instead of stopping at
+    /// the first one.
     pub fn scan_tokens(&mut self) -> Result<Vec<Token>, Vec<AliceError>> {
         let mut tokens = Vec::new();
         let mut errors = Vec::new();
-        let mut is_error = false;
 
-        while !self.is_at_end() {
+        for token in self {
+            match token {
+                Ok(token) => tokens.push(token),
+                Err(e) => errors.push(e)
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(tokens)
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn scan_token(&mut self) -> Result<Token, AliceError> {
+        loop {
+            if self.is_at_end() {
+                self.finished = true;
+                return Ok(self.eof());
+            }
+
+            self.start = self.current;
+            self.start_line = self.line;
+            self.start_column = self.column_at(self.start);
+
             let byte = self.advance();
-            match byte {
-                b'(' => tokens.push(Token{r#type: TokenType::LeftParen,     lexeme: None, literal: None, line: self.line}),
-                b')' => tokens.push(Token{r#type: TokenType::RightParen,    lexeme: None, literal: None, line: self.line}),
-                b'[' => tokens.push(Token{r#type: TokenType::LeftSquare,    lexeme: None, literal: None, line: self.line}),
-                b']' => tokens.push(Token{r#type: TokenType::RightSquare,   lexeme: None, literal: None, line: self.line}),
-                b'{' => tokens.push(Token{r#type: TokenType::LeftBrace,     lexeme: None, literal: None, line: self.line}),
-                b'}' => tokens.push(Token{r#type: TokenType::RightBrace,    lexeme: None, literal: None, line: self.line}),
-                b',' => tokens.push(Token{r#type: TokenType::Comma,         lexeme: None, literal: None, line: self.line}),
-                b'.' => tokens.push(Token{r#type: TokenType::Dot,           lexeme: None, literal: None, line: self.line}),
-                b'-' => tokens.push(Token{r#type: TokenType::Minus,         lexeme: None, literal: None, line: self.line}),
-                b'+' => tokens.push(Token{r#type: TokenType::Plus,          lexeme: None, literal: None, line: self.line}),
-                b';' => tokens.push(Token{r#type: TokenType::Semicolon,     lexeme: None, literal: None, line: self.line}),
-                b'*' => tokens.push(Token{r#type: TokenType::Star,          lexeme: None, literal: None, line: self.line}),
-                b'%' => tokens.push(Token{r#type: TokenType::PercentSign,   lexeme: None, literal: None, line: self.line }),
+            let token_type = match byte {
+                b'(' => TokenType::LeftParen,
+                b')' => TokenType::RightParen,
+                b'[' => TokenType::LeftSquare,
+                b']' => TokenType::RightSquare,
+                b'{' => TokenType::LeftBrace,
+                b'}' => TokenType::RightBrace,
+                b',' => TokenType::Comma,
+                b'.' => TokenType::Dot,
+                b':' => TokenType::Colon,
+                b'-' => TokenType::Minus,
+                b'+' => TokenType::Plus,
+                b';' => TokenType::Semicolon,
+                b'*' => TokenType::Star,
+                b'%' => TokenType::PercentSign,
 
                 b'!' => {
-                    let token_type = if self.matching(b'=') {
+                    if self.matching(b'=') {
                         TokenType::BangEqual
                     } else {
                         TokenType::Bang
-                    };
-                    tokens.push(Token{r#type: token_type, lexeme: None, literal: None, line: self.line});
+                    }
                 }
                 b'=' => {
-                    let token_type = if self.matching(b'=') {
+                    if self.matching(b'=') {
                         TokenType::EqualEqual
                     } else if self.matching(b'>') {
                         TokenType::Arrowhead
                     } else {
                         TokenType::Equal
-                    };
-                    tokens.push(Token{r#type: token_type, lexeme: None, literal: None, line: self.line});
+                    }
                 }
                 b'<' => {
-                    let token_type = if self.matching(b'=') {
+                    if self.matching(b'=') {
                         TokenType::LessEqual
                     } else {
                         TokenType::Less
-                    };
-                    tokens.push(Token{r#type: token_type, lexeme: None, literal: None, line: self.line});
+                    }
                 }
                 b'>' => {
-                    let token_type = if self.matching(b'=') {
+                    if self.matching(b'=') {
                         TokenType::GreaterEqual
                     } else {
                         TokenType::Greater
-                    };
-                    tokens.push(Token{r#type: token_type, lexeme: None, literal: None, line: self.line});
+                    }
                 }
                 b'/' => {
                     if self.matching(b'/') {
+                        // `///` starts a doc comment, `////` does not.
+                        let doc = self.peek() == b'/' && self.peek_next() != b'/';
                         while self.peek() != b'\n' && !self.is_at_end() {
                             self.advance();
                         }
-                    } else {
-                        tokens.push(Token{r#type: TokenType::Slash, lexeme: None, literal: None, line: self.line});
+                        if doc {
+                            let text = &self.source[self.start + 3..self.current];
+                            let text = text.strip_prefix(' ').unwrap_or(text).trim_end_matches('\r');
+                            return Ok(self.token(TokenType::DocComment, None, Some(Literal::String(text.into()))));
+                        }
+                        continue;
+                    }
+                    if self.matching(b'*') {
+                        self.block_comment()?;
+                        continue;
                     }
+                    TokenType::Slash
                 }
 
                 b' ' |
                 b'\r'|
                 b'\t' => continue,
-                b'\n' => self.line += 1,
+                b'\n' => {
+                    self.newline();
+                    continue;
+                }
 
                 b'"' => {
-                    match self.string() {
-                        Ok(literal) => tokens.push(Token{r#type: TokenType::String, lexeme: None, literal, line: self.line}),
-                        Err(e) => {
-                            is_error = true;
-                            errors.push(e);
-                        }
-                    }
+                    let literal = self.string()?;
+                    return Ok(self.token(TokenType::String, None, literal));
                 }
 
                 _ => {
                     if self.is_digit(byte) {
-                        match self.number() {
-                            Ok((r#type, literal)) => {
-                                tokens.push(Token{r#type, lexeme: None, literal, line: self.line});
-                            }
-                            Err(e) => {
-                                is_error = true;
-                                errors.push(e);
-                            }
-                        }
-                    } else if self.is_alpha(byte) {
+                        let (r#type, literal) = self.number()?;
+                        return Ok(self.token(r#type, None, literal));
+                    }
+
+                    // Work on the whole character, not just its first byte.
+                    let c = self.source[self.start..].chars().next().unwrap_or_default();
+                    self.current = self.start + c.len_utf8();
+
+                    if c == '_' || unicode::is_xid_start(c) {
                         let (r#type, lexeme, literal) = self.identifier();
-                        tokens.push(Token { r#type, lexeme, literal, line: self.line })
+                        return Ok(self.token(r#type, lexeme, literal));
                     } else {
-                        is_error = true;
-                        errors.push(AliceError::SyntaxError(format!("unknown token '{}'.", byte as char).into(), self.line));
+                        return Err(self.error(format!("unknown token '{}'.", c)));
                     }
                 }
-            }
-        }
+            };
 
-        if is_error {
-            return Err(errors);
+            return Ok(self.token(token_type, None, None));
         }
+    }
 
-        tokens.push(Token {
-            r#type: TokenType::Eof,
-            lexeme: None,
-            literal: None,
-            line: self.line - 1
-        });
+    /// Skips a `/* ... */` comment, which may contain nested ones.
+    fn block_comment(&mut self) -> Result<(), AliceError> {
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                self.unterminated = true;
+                let suggestion = if depth == 1 {
+                    "add '*/' to close it".to_string()
+                } else {
+                    format!("add {} '*/' to close it and the comments nested in it", depth)
+                };
+                return Err(self.error("unterminated comment.").with_suggestion(suggestion));
+            }
+
+            match self.advance() {
+                b'\n' => self.newline(),
+                b'/' if self.matching(b'*') => depth += 1,
+                b'*' if self.matching(b'/') => depth -= 1,
+                _ => ()
+            }
+        }
 
-        Ok(tokens)
+        Ok(())
     }
 
-    fn string<'a>(&mut self) -> Result<Option<Literal>, AliceError> {
-        let start_index = self.current;
+    /// Scans the rest of a string literal, which may span lines and
+    /// contain the escapes `\"`, `\\`, `\n`, `\r`, `\t`, `\0` and `\u{7FFF}`.
+    fn string(&mut self) -> Result<Option<Literal>, AliceError> {
+        let mut text = String::new();
+        let mut unescaped = self.current;
+        let mut invalid = None;
 
         while self.peek() != b'"' && !self.is_at_end() {
-            if self.peek() == b'\n' {
-                self.line += 1;
+            match self.advance() {
+                b'\n' => self.newline(),
+                b'\\' => {
+                    text.push_str(&self.source[unescaped..self.current - 1]);
+                    match self.escape() {
+                        Ok(c) => text.push(c),
+                        Err(e) => invalid = invalid.or(Some(e))
+                    }
+                    unescaped = self.current;
+                }
+                _ => ()
             }
-            self.advance();
         }
 
         if self.is_at_end() {
-            return Err(AliceError::SyntaxError("not a full string.".into(), self.line))
+            self.unterminated = true;
+            return Err(self.error("not a full string.")
+                .with_suggestion("add a closing '\"' to end the string"))
         }
 
-        let str = String::from_utf8(self.source[start_index..self.current].to_vec()).unwrap();
-
+        text.push_str(&self.source[unescaped..self.current]);
         self.advance();
 
-        Ok(Some(Literal::String(str)))
+        match invalid {
+            Some(e) => Err(e),
+            None => Ok(Some(Literal::String(AliceString::from(text.as_str()))))
+        }
+    }
+
+    /// The character an escape stands for, just after its backslash.
+    fn escape(&mut self) -> Result<char, AliceError> {
+        let start = self.current - 1;
+        let error = |scanner: &Scanner, msg: String| {
+            let span = Span { start, end: scanner.current };
+            AliceError::syntax(msg, scanner.line, column(&scanner.source[scanner.line_start..start]), span)
+        };
+
+        let Some(c) = self.source[self.current..].chars().next() else {
+            return Err(error(self, "unknown escape '\\'.".to_owned()));
+        };
+        self.current += c.len_utf8();
+
+        match c {
+            '"' => Ok('"'),
+            '\\' => Ok('\\'),
+            'n' => Ok('\n'),
+            'r' => Ok('\r'),
+            't' => Ok('\t'),
+            '0' => Ok('\0'),
+            'u' => {
+                let digits = match self.source[self.current..].strip_prefix('{').and_then(|rest| rest.split_once('}')) {
+                    Some((digits, _)) if (1..=6).contains(&digits.len()) && digits.bytes().all(|b| b.is_ascii_hexdigit()) => digits,
+                    _ => return Err(error(self, "invalid unicode escape.".to_owned())
+                        .with_suggestion("write it as '\\u{' followed by 1 to 6 hex digits and '}'"))
+                };
+                self.current += digits.len() + 2;
+
+                match u32::from_str_radix(digits, 16).ok().and_then(char::from_u32) {
+                    Some(c) => Ok(c),
+                    None => Err(error(self, format!("'{}' is not a unicode character.", digits)))
+                }
+            }
+            '\n' => {
+                let e = error(self, "unknown escape '\\' at the end of a line.".to_owned());
+                self.newline();
+                Err(e)
+            }
+            c => Err(error(self, format!("unknown escape '\\{}'.", c))
+                .with_suggestion("write '\\\\' for a backslash"))
+        }
     }
 
-    fn number<'a>(&mut self) -> Result<(TokenType, Option<Literal>), AliceError> {
+    fn number(&mut self) -> Result<(TokenType, Option<Literal>), AliceError> {
         let mut is_double = false;
         let start_index = self.current - 1;
         while self.is_digit(self.peek()) {
@@ -166,44 +366,92 @@ impl Scanner {
             }
         }
 
-        let num = String::from_utf8(self.source[start_index..self.current].to_vec()).unwrap();
-        
+        let num = &self.source[start_index..self.current];
+
         if is_double {
-            let num = num.parse::<f64>().unwrap();
-            Ok((TokenType::F64, Some(Literal::F64(num))))
+            match num.parse::<f64>() {
+                Ok(num) => Ok((TokenType::F64, Some(Literal::F64(num)))),
+                Err(_) => Err(self.error(format!("invalid number literal '{}'.", num)))
+            }
         } else {
-            let num = num.parse::<i64>().unwrap();
-            Ok((TokenType::I64, Some(Literal::I64(num))))
+            match num.parse::<i64>() {
+                Ok(num) => Ok((TokenType::I64, Some(Literal::I64(num)))),
+                Err(_) => Err(self.error(format!("integer literal '{}' does not fit in an i64.", num))
+                    .with_note(format!("the largest i64 is {}", i64::MAX))
+                    .with_suggestion("write it as a float by adding '.0'"))
+            }
         }
     }
 
-    fn identifier<'a>(&mut self) -> (TokenType, Option<String>, Option<Literal>) {
-        let start_index = self.current - 1;
-        while self.is_alpha_numeric(self.peek()) {
-            self.advance();
-        }
+    fn identifier(&mut self) -> (TokenType, Option<Symbol>, Option<Literal>) {
+        let rest = &self.source[self.current..];
+        self.current += rest.find(|c| !unicode::is_xid_continue(c)).unwrap_or(rest.len());
 
-        let id = String::from_utf8(self.source[start_index..self.current].to_vec()).unwrap();
-        match id.as_str() {
-            "and"       =>    (TokenType::And,      None, None),
-            "or"        =>    (TokenType::Or,       None, None),
-            "if"        =>    (TokenType::If,       None, None),
-            "else"      =>    (TokenType::Else,     None, None),
-            "true"      =>    (TokenType::True,     None, None),
-            "false"     =>    (TokenType::False,    None, None),
-            "fn"        =>    (TokenType::Fn,       None, None),
-            "let"       =>    (TokenType::Let,      None, None),
-            "nil"       =>    (TokenType::Nil,      None, None),
-            "println"   =>    (TokenType::Println,  None, None),
-            "return"    =>    (TokenType::Return,   None, None),
-            "for"       =>    (TokenType::For,      None, None),
-            "in"        =>    (TokenType::In,       None, None),
-            _ => {
-                (TokenType::Identifier, Some(id.clone()), Some(Literal::Id(id)))
+        let id = &self.source[self.start..self.current];
+        match KEYWORDS.iter().find(|(keyword, _)| *keyword == id) {
+            Some(&(_, r#type)) => (r#type, None, None),
+            None => {
+                let symbol = Symbol::intern(id);
+                (TokenType::Identifier, Some(symbol.clone()), Some(Literal::Id(symbol)))
             }
         }
     }
 
+    /// The end of input, placed at the end of the last line rather than
+    /// on the empty line after a trailing newline.
+    fn eof(&self) -> Token {
+        let (line, line_start) = if self.current == self.line_start && self.line > 1 {
+            let previous = self.source[..self.line_start - 1].rfind('\n').map_or(0, |i| i + 1);
+            (self.line - 1, previous)
+        } else {
+            (self.line, self.line_start)
+        };
+        let end = self.source[..self.current].trim_end_matches(['\n', '\r']).len().max(line_start);
+
+        Token {
+            r#type: TokenType::Eof,
+            lexeme: None,
+            literal: None,
+            line,
+            column: column(&self.source[line_start..end]),
+            span: Span { start: self.current, end: self.current }
+        }
+    }
+
+    #[inline]
+    fn token(&self, r#type: TokenType, lexeme: Option<Symbol>, literal: Option<Literal>) -> Token {
+        Token {
+            r#type,
+            lexeme,
+            literal,
+            line: self.start_line,
+            column: self.start_column,
+            span: Span { start: self.start, end: self.current }
+        }
+    }
+
+    #[inline]
+    fn error(&self, msg: impl Into<Box<str>>) -> AliceError {
+        AliceError::syntax(msg, self.start_line, self.start_column, Span { start: self.start, end: self.current })
+    }
+
+    #[inline]
+    fn newline(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+        self.column = 1;
+        self.column_offset = self.current;
+    }
+
+    /// The column of `offset`, which is on the current line and no earlier
+    /// than the last offset asked about.
+    #[inline]
+    fn column_at(&mut self, offset: usize) -> u32 {
+        self.column += self.source[self.column_offset..offset].chars().count() as u32;
+        self.column_offset = offset;
+        self.column
+    }
+
     #[inline]
     fn is_at_end(&self) -> bool {
         self.current >= self.source.len()
@@ -212,25 +460,17 @@ impl Scanner {
     #[inline]
     fn advance(&mut self) -> u8 {
         self.current += 1;
-        *self.source.get(self.current - 1).unwrap()
+        self.source.as_bytes()[self.current - 1]
     }
 
     #[inline]
     fn peek(&self) -> u8 {
-        if self.is_at_end() {
-            return b'\0'
-        }
-
-        *self.source.get(self.current).unwrap()
+        self.source.as_bytes().get(self.current).copied().unwrap_or(b'\0')
     }
 
     #[inline]
     fn peek_next(&self) -> u8 {
-        if self.current + 1 >= self.source.len() {
-            return b'\0'
-        }
-
-        *self.source.get(self.current + 1).unwrap()
+        self.source.as_bytes().get(self.current + 1).copied().unwrap_or(b'\0')
     }
 
     #[inline]
@@ -247,20 +487,20 @@ impl Scanner {
         true
     }
 
-    #[inline]
-    fn is_alpha_numeric(&self, c: u8) -> bool {
-        self.is_alpha(c) || self.is_digit(c)
-    }
-
     #[inline]
     fn is_digit(&self, c: u8) -> bool {
-        c >= b'0' && c <= b'9'
+        c.is_ascii_digit()
     }
+}
 
-    #[inline]
-    fn is_alpha(&self, c: u8) -> bool {
-        c >= b'a' && c <= b'z' ||
-        c >= b'A' && c <= b'Z' ||
-        c == b'_'
+impl Iterator for Scanner<'_> {
+    type Item = Result<Token, AliceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        Some(self.scan_token())
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,109 @@
+use std::{rc::Rc, fmt::{Debug, Display, Write}, ops::Deref};
+
+/// Longest string stored inline, chosen so an `AliceObject` holding an
+/// `AliceString` stays two words wide.
+const INLINE: usize = 14;
+
+/// An immutable string value.
+///
+/// Strings of up to `INLINE` bytes live inside the value itself, so short
+/// keys and concatenations never allocate; longer ones share a single heap
+/// allocation between clones.
+#[derive(Clone)]
+pub struct AliceString(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Inline { len: u8, bytes: [u8; INLINE] },
+    Heap(Rc<String>)
+}
+
+impl AliceString {
+    /// Formats straight into a new string, without an intermediate `String`
+    /// when the result fits inline.
+    pub fn format(args: std::fmt::Arguments) -> AliceString {
+        let mut builder = Builder(Repr::Inline { len: 0, bytes: [0; INLINE] });
+        builder.write_fmt(args).expect("formatting into memory does not fail");
+        AliceString(builder.0)
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            Repr::Inline { len, bytes } => std::str::from_utf8(&bytes[..*len as usize]).expect("inline strings hold whole characters"),
+            Repr::Heap(str) => str
+        }
+    }
+}
+
+/// Accumulates formatted output inline until it outgrows `INLINE` bytes.
+struct Builder(Repr);
+
+impl Write for Builder {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        match &mut self.0 {
+            Repr::Inline { len, bytes } if *len as usize + s.len() <= INLINE => {
+                bytes[*len as usize..*len as usize + s.len()].copy_from_slice(s.as_bytes());
+                *len += s.len() as u8;
+            }
+            Repr::Inline { len, bytes } => {
+                let mut str = String::with_capacity(2 * (*len as usize + s.len()));
+                str.push_str(std::str::from_utf8(&bytes[..*len as usize]).expect("inline strings hold whole characters"));
+                str.push_str(s);
+                self.0 = Repr::Heap(Rc::new(str));
+            }
+            Repr::Heap(str) => Rc::get_mut(str).expect("builder owns its string").push_str(s)
+        }
+        Ok(())
+    }
+}
+
+impl From<&str> for AliceString {
+    fn from(str: &str) -> Self {
+        if str.len() <= INLINE {
+            let mut bytes = [0; INLINE];
+            bytes[..str.len()].copy_from_slice(str.as_bytes());
+            AliceString(Repr::Inline { len: str.len() as u8, bytes })
+        } else {
+            AliceString(Repr::Heap(Rc::new(str.to_string())))
+        }
+    }
+}
+
+impl From<String> for AliceString {
+    fn from(str: String) -> Self {
+        if str.len() <= INLINE {
+            AliceString::from(str.as_str())
+        } else {
+            AliceString(Repr::Heap(Rc::new(str)))
+        }
+    }
+}
+
+impl Deref for AliceString {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for AliceString {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Display for AliceString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self.as_str(), f)
+    }
+}
+
+impl Debug for AliceString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
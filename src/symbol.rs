@@ -0,0 +1,87 @@
+use std::{cell::RefCell, collections::HashSet, fmt::{Debug, Display}, hash::{Hash, Hasher}, rc::Rc};
+
+/// An interned identifier.
+///
+/// Every `Symbol` for a name shares one allocation, so symbols are compared
+/// and hashed by address rather than by their text. A name stays interned
+/// only while some `Symbol` for it is alive, so scripts and embedders that
+/// come and go don't grow the table for good.
+///
+/// The table is per thread, and a symbol only means something on the thread
+/// that interned it; holding an `Rc` keeps `Symbol` from being `Send`.
+#[derive(Clone)]
+pub struct Symbol(Rc<str>);
+
+thread_local! {
+    static INTERNER: RefCell<HashSet<Rc<str>>> = RefCell::new(HashSet::new());
+}
+
+impl Symbol {
+    pub fn intern(name: &str) -> Symbol {
+        INTERNER.with(|interner| {
+            let mut interner = interner.borrow_mut();
+            if let Some(name) = interner.get(name) {
+                return Symbol(name.clone());
+            }
+
+            let name: Rc<str> = name.into();
+            interner.insert(name.clone());
+            Symbol(name)
+        })
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Drop for Symbol {
+    fn drop(&mut self) {
+        // The table holds the other reference: this is the last symbol for
+        // the name. The table may already be gone while the thread exits.
+        if Rc::strong_count(&self.0) == 2 {
+            let _ = INTERNER.try_with(|interner| {
+                if let Ok(mut interner) = interner.try_borrow_mut() {
+                    interner.remove(&*self.0);
+                }
+            });
+        }
+    }
+}
+
+impl Default for Symbol {
+    /// The empty name.
+    #[inline]
+    fn default() -> Self {
+        Symbol::intern("")
+    }
+}
+
+impl PartialEq for Symbol {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (Rc::as_ptr(&self.0) as *const u8 as usize).hash(state);
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
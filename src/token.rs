@@ -1,15 +1,26 @@
+use crate::{string::AliceString, symbol::Symbol};
+
 #[derive(Debug, Clone)]
 pub struct Token {
     pub r#type: TokenType,
-    pub lexeme: Option<String>,
+    pub(crate) lexeme: Option<Symbol>,
     pub literal: Option<Literal>,
-    pub line: u32
+    pub line: u32,
+    pub column: u32,
+    pub span: Span
+}
+
+/// Byte range of a token in the source.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize
 }
 
 #[derive(Debug, Clone)]
 pub enum Literal {
-    Id(String),
-    String(String),
+    Id(Symbol),
+    String(AliceString),
     Array(Vec<Literal>),
     I64(i64),
     F64(f64),
@@ -22,7 +33,7 @@ pub enum Literal {
 pub enum TokenType {
     // Single-character tokens.
     LeftParen, RightParen, LeftBrace, RightBrace, LeftSquare, RightSquare,
-    Comma, Dot, Minus, Plus, Slash, Star, PercentSign, Semicolon,
+    Comma, Dot, Colon, Minus, Plus, Slash, Star, PercentSign, Semicolon,
 
     // One or two character tokens.
     Bang, BangEqual,
@@ -35,7 +46,24 @@ pub enum TokenType {
     Identifier, String, I64, F64, Array, True, False, Nil,
 
     // Keywords.
-    And, Or, For, In, If, Else, Fn, Println, Return, Let,
+    And, Or, For, In, If, Else, Fn, Return, Let,
+
+    // `///` comments; the parser attaches them to the next declaration.
+    DocComment,
 
     Eof
-}
\ No newline at end of file
+}
+impl Token {
+    /// The identifier this token names, or the empty name for tokens that
+    /// don't carry one.
+    #[inline]
+    pub(crate) fn symbol(&self) -> Symbol {
+        self.lexeme.clone().unwrap_or_default()
+    }
+
+    /// Like `symbol`, borrowing the name.
+    #[inline]
+    pub(crate) fn name(&self) -> &str {
+        self.lexeme.as_ref().map_or("", Symbol::as_str)
+    }
+}
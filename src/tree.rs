@@ -0,0 +1,405 @@
+use std::{rc::Rc, cell::RefCell};
+
+use crate::{engine::{Core, Engine, sealed::Sealed}, environment::Environment, heap::Heap, host::{Host, OsHost}, ast::{Expr, Stmt, AliceObject, Binding, CallSite, VisitExpr, VisitStmt}, error::AliceError, function::{AliceFunction, Body}, native::Arity, ops, token::{Token, TokenType}};
+
+/// What happens after a statement runs: carry on with the next one, or
+/// unwind to the enclosing call with a return value.
+///
+/// `return f(x);` unwinds with `TailCall` instead of calling `f` itself, and
+/// `Interpreter::call` runs `f` in place of the returning function, so tail
+/// recursion does not grow the Rust stack.
+pub enum Flow {
+    Next,
+    Return(AliceObject),
+    TailCall(Rc<AliceFunction>, Vec<AliceObject>, Token)
+}
+
+/// Tree-walking interpreter. Output, input and the like go through the
+/// `Host` `H`, the process's own unless given another with `with_host`.
+///
+/// Every call that isn't a tail call recurses on the native stack, up to
+/// 1000 calls deep: a few megabytes in release builds, and over ten in
+/// debug ones, more than a spawned thread has by default.
+pub struct Interpreter<H: Host = OsHost> {
+    host: Rc<H>,
+    core: Core,
+    environment: Option<Rc<RefCell<Environment>>>,
+    depth: usize,
+    heap: Heap
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    #[inline]
+    pub fn new() -> Interpreter {
+        Interpreter::with_host(OsHost::default())
+    }
+}
+
+impl<H: Host> Interpreter<H> {
+    pub fn with_host(host: H) -> Interpreter<H> {
+        let host = Rc::new(host);
+        Interpreter { core: Core::new(host.clone()), host, environment: None, depth: 0, heap: Heap::default() }
+    }
+
+    #[inline]
+    pub fn host(&self) -> &H {
+        &self.host
+    }
+
+    #[inline]
+    fn interpret(&mut self, statements: &[Stmt]) -> Result<(), AliceError> {
+        for stmt in statements {
+            self.execute(stmt)?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs `statements`, evaluating a trailing bare expression to its value.
+    fn interpret_value(&mut self, statements: &[Stmt]) -> Result<AliceObject, AliceError> {
+        let Some((last, rest)) = statements.split_last() else {
+            return Ok(AliceObject::Nil);
+        };
+        self.interpret(rest)?;
+
+        match last.bare_expression() {
+            Some(expression) => self.evaluate(expression),
+            None => self.interpret(std::slice::from_ref(last)).map(|_| AliceObject::Nil)
+        }
+    }
+
+    fn execute_block(&mut self, statements: &[Stmt], environment: Rc<RefCell<Environment>>) -> Result<Flow, AliceError> {
+        let previous = self.environment.replace(environment);
+
+        let result = self.execute_stmts(statements);
+
+        self.environment = previous;
+
+        result
+    }
+
+    fn execute_stmts(&mut self, statements: &[Stmt]) -> Result<Flow, AliceError> {
+        for stmt in statements {
+            match self.execute(stmt)? {
+                Flow::Next => (),
+                flow => return Ok(flow)
+            }
+        }
+
+        Ok(Flow::Next)
+    }
+
+    #[inline]
+    fn scope(&mut self) -> Rc<RefCell<Environment>> {
+        self.heap.environment(Environment::from(self.environment.clone()))
+    }
+
+    #[inline]
+    fn define(&mut self, name: &Token, value: AliceObject) {
+        match &self.environment {
+            Some(environment) => environment.borrow_mut().define(value),
+            None => self.core.globals.define(name.symbol(), value)
+        }
+    }
+
+    fn execute_array(&mut self, list: AliceObject, body: &[Stmt]) -> Result<Flow, AliceError> {
+        // Iterates a live view of the array: the loop sees items appended or
+        // replaced by its body, and stops at the array's current length.
+        if let AliceObject::Array(array) = list {
+            let mut index = 0;
+            loop {
+                let Some(item) = array.borrow().get(index).cloned() else { break };
+                index += 1;
+                let environment = self.scope();
+                environment.borrow_mut().define(item);
+                match self.execute_block(body, environment)? {
+                    Flow::Next => (),
+                    flow => return Ok(flow)
+                }
+            }
+        };
+
+        Ok(Flow::Next)
+    }
+
+    fn execute_range(&mut self, range: AliceObject, body: &[Stmt]) -> Result<Flow, AliceError> {
+        if let AliceObject::Range(range) = range {
+            let mut index = range.start;
+            while index < range.end {
+                let environment = self.scope();
+                environment.borrow_mut().define(AliceObject::I64(index));
+                match self.execute_block(body, environment)? {
+                    Flow::Next => (),
+                    flow => return Ok(flow)
+                }
+
+                index += 1;
+            }
+        };
+
+        Ok(Flow::Next)
+    }
+
+    fn call(&mut self, function: &AliceFunction, paren: &Token, arguments: Vec<AliceObject>) -> Result<AliceObject, AliceError> {
+        self.depth += 1;
+        let result = self.call_tail(function, paren, arguments);
+        self.depth -= 1;
+
+        result
+    }
+
+    /// Runs `function`, then each function it tail calls in its place.
+    fn call_tail(&mut self, function: &AliceFunction, paren: &Token, arguments: Vec<AliceObject>) -> Result<AliceObject, AliceError> {
+        let mut flow = self.invoke(function, paren, arguments)?;
+        while let Flow::TailCall(function, arguments, paren) = flow {
+            flow = self.invoke(&function, &paren, arguments)?;
+        }
+
+        match flow {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(AliceObject::Nil)
+        }
+    }
+
+    fn invoke(&mut self, function: &AliceFunction, paren: &Token, arguments: Vec<AliceObject>) -> Result<Flow, AliceError> {
+        if arguments.len() != function.arity() {
+            return Err(AliceError::runtime(Arity::Exact(function.arity()).mismatch(arguments.len()), paren));
+        }
+
+        let Body::Ast(body) = &function.body else {
+            return Err(AliceError::runtime("Can only call functions.", paren));
+        };
+        if self.depth > self.core.max_call_depth {
+            return Err(AliceError::runtime("Stack overflow.", paren));
+        }
+
+        let environment = self.heap.environment(Environment::from(function.closure.clone()));
+        for argument in arguments {
+            environment.borrow_mut().define(argument);
+        }
+
+        self.execute_block(body, environment)
+    }
+}
+
+impl<H: Host> Sealed for Interpreter<H> {
+    #[inline]
+    fn core(&self) -> &Core {
+        &self.core
+    }
+
+    #[inline]
+    fn core_mut(&mut self) -> &mut Core {
+        &mut self.core
+    }
+
+    #[inline]
+    fn heap(&self) -> &Heap {
+        &self.heap
+    }
+
+    #[inline]
+    fn heap_mut(&mut self) -> &mut Heap {
+        &mut self.heap
+    }
+
+    #[inline]
+    fn run_statements(&mut self, statements: &[Stmt]) -> Result<AliceObject, AliceError> {
+        self.interpret_value(statements)
+    }
+}
+
+impl<H: Host> Engine for Interpreter<H> {}
+
+impl<H: Host> VisitExpr<AliceObject> for Interpreter<H> {
+    fn visit_grouping_expr(&mut self, expression: &Expr) -> Result<AliceObject, AliceError> {
+        self.evaluate(expression)
+    }
+
+    fn visit_variable_expr(&mut self, name: &Token, binding: Option<Binding>) -> Result<AliceObject, AliceError> {
+        match (binding, &self.environment) {
+            (Some(Binding { depth, slot }), Some(environment)) => Ok(environment.borrow().get_at(depth, slot)),
+            _ => self.core.globals.get(name)
+        }
+    }
+
+    fn visit_assign_expr(&mut self, name: &Token, value: &Expr, binding: Option<Binding>) -> Result<AliceObject, AliceError> {
+        let value = self.evaluate(value)?;
+        match (binding, &self.environment) {
+            (Some(Binding { depth, slot }), Some(environment)) => environment.borrow_mut().assign_at(depth, slot, value.clone()),
+            _ => self.core.globals.assign(name, value.clone())?
+        }
+        Ok(value)
+    }
+
+    fn visit_unary_expr(&mut self, operator: &Token, value: &Expr) -> Result<AliceObject, AliceError> {
+        let value = self.evaluate(value)?;
+        ops::unary(operator, value)
+    }
+
+    fn visit_binary_expr(&mut self, left: AliceObject, operator: &Token, right: &Expr) -> Result<AliceObject, AliceError> {
+        let right = self.evaluate(right)?;
+        ops::binary(left, operator, right)
+    }
+
+    fn visit_logical_expr(&mut self, left: AliceObject, operator: &Token, right: &Expr) -> Result<AliceObject, AliceError> {
+        if operator.r#type == TokenType::Or {
+            if ops::is_truthy(&left) {
+                return Ok(left);
+            }
+        } else {
+            if !ops::is_truthy(&left) {
+                return Ok(left);
+            }
+        }
+
+        self.evaluate(right)
+    }
+
+    fn visit_call_expr(&mut self, callee: &Expr, site: &Rc<CallSite>, arguments: &[Expr]) -> Result<AliceObject, AliceError> {
+        let callee = self.evaluate(callee)?;
+
+        let mut values = Vec::new();
+        for argument in arguments {
+            values.push(self.evaluate(argument)?);
+        }
+
+        match callee {
+            AliceObject::Function(function) => {
+                site.positional_only()?;
+                self.call(&function, &site.paren, values)
+            }
+            AliceObject::Native(function) => function.call(&mut self.heap, site, &values),
+            _ => Err(AliceError::runtime("Can only call functions.", &site.paren))
+        }
+    }
+
+    fn visit_literal_expr(&mut self, value: &AliceObject) -> Result<AliceObject, AliceError> {
+        Ok(self.heap.literal(value))
+    }
+
+    fn visit_array_expr(&mut self, list: &[Expr]) -> Result<AliceObject, AliceError> {
+        let mut values = Vec::new();
+        for expr in list {
+            values.push(self.evaluate(expr)?);
+        }
+        Ok(self.heap.array(values))
+    }
+
+    fn visit_index_expr(&mut self, object: &Expr, bracket: &Token, index: &Expr) -> Result<AliceObject, AliceError> {
+        let object = self.evaluate(object)?;
+        let index = self.evaluate(index)?;
+        ops::get_index(object, bracket, index)
+    }
+
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> Result<AliceObject, AliceError> {
+        let object = self.evaluate(object)?;
+        ops::get_member(object, name)
+    }
+
+    fn visit_set_index_expr(&mut self, object: &Expr, bracket: &Token, index: &Expr, value: &Expr) -> Result<AliceObject, AliceError> {
+        let object = self.evaluate(object)?;
+        let index = self.evaluate(index)?;
+        let value = self.evaluate(value)?;
+        ops::set_index(object, bracket, index, value.clone())?;
+        Ok(value)
+    }
+
+    fn visit_range_expr(&mut self, start: &Expr, dots: &Token, end: &Expr) -> Result<AliceObject, AliceError> {
+        let start = self.evaluate(start)?;
+        let end = self.evaluate(end)?;
+        ops::range(start, dots, end)
+    }
+}
+
+impl<H: Host> VisitStmt<Flow> for Interpreter<H> {
+    fn visit_return_stmt(&mut self, _keyword: &Token, value: Option<&Expr>) -> Result<Flow, AliceError> {
+        if let Some(Expr::Call { callee, site, arguments }) = value.map(Expr::unparenthesized) {
+            let callee = self.evaluate(callee)?;
+
+            let mut values = Vec::new();
+            for argument in arguments {
+                values.push(self.evaluate(argument)?);
+            }
+
+            return match callee {
+                AliceObject::Function(function) => {
+                    site.positional_only()?;
+                    Ok(Flow::TailCall(function, values, site.paren.clone()))
+                }
+                AliceObject::Native(function) => Ok(Flow::Return(function.call(&mut self.heap, site, &values)?)),
+                _ => Err(AliceError::runtime("Can only call functions.", &site.paren))
+            };
+        }
+
+        let value = match value {
+            Some(value) => self.evaluate(value)?,
+            None => AliceObject::Nil
+        };
+        Ok(Flow::Return(value))
+    }
+
+    fn visit_var_stmt(&mut self, name: &Token, initializer: Option<&Expr>) -> Result<Flow, AliceError> {
+        if let Some(expr) = initializer {
+            match self.evaluate(expr) {
+                Ok(v) => self.define(name, v),
+                Err(e) => return Err(e)
+            };
+        } else {
+            self.define(name, AliceObject::Nil);
+        }
+        Ok(Flow::Next)
+    }
+
+    fn visit_block_stmt(&mut self, statements: &[Stmt]) -> Result<Flow, AliceError> {
+        let environment = self.scope();
+        self.execute_block(statements, environment)
+    }
+
+    fn visit_fn_stmt(&mut self, name: &Token, params: &[Token], body: &Rc<Vec<Stmt>>) -> Result<Flow, AliceError> {
+        let function = AliceFunction {
+            name: name.clone(),
+            params: params.to_vec(),
+            body: Body::Ast(body.clone()),
+            closure: self.environment.clone()
+        };
+        self.define(name, AliceObject::Function(Rc::new(function)));
+        Ok(Flow::Next)
+    }
+
+    fn visit_if_stmt(&mut self, condition: &Expr, then_branch: &Stmt, else_branch: Option<&Stmt>) -> Result<Flow, AliceError> {
+        let v = self.evaluate(condition)?;
+        if ops::is_truthy(&v) {
+            self.execute(then_branch)
+        } else if let Some(else_branch) = else_branch {
+            self.execute(else_branch)
+        } else {
+            Ok(Flow::Next)
+        }
+    }
+
+    fn visit_expression_stmt(&mut self, expression: &Expr) -> Result<Flow, AliceError> {
+        match self.evaluate(expression) {
+            Ok(_) => Ok(Flow::Next),
+            Err(e) => Err(e)
+        }
+    }
+
+    fn visit_for_stmt(&mut self, value: &Token, expression: &Expr, body: &[Stmt]) -> Result<Flow, AliceError> {
+        let object = self.evaluate(expression)?;
+
+        if let AliceObject::Array(..) = &object {
+            self.execute_array(object, body)
+        } else if let AliceObject::Range(..) = &object {
+            self.execute_range(object, body)
+        } else {
+            Err(AliceError::runtime("Expect Array or Range(..) expression.", value))
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,355 @@
+use std::{collections::HashMap, fmt::{Debug, Display}, hash::BuildHasher, rc::Rc};
+
+use crate::{ast::{AliceObject, AliceRange}, display, error::AliceError, native::NativeModule};
+
+// Values as embedders see them. Unlike the engines' own representation they
+// own their contents: an array is copied out of the program, so changing a
+// `Value` never changes the program's array, and an array that contains
+// itself can't be converted at all.
+//
+// A failed conversion is an `AliceError::native`, so a native function can
+// use `?` on it and have the error reported at the call.
+
+/// A value of an Alice program.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Nil,
+    Boolean(bool),
+    I64(i64),
+    F64(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// The half-open range `start..end`.
+    Range(i64, i64),
+    Function(Function),
+    Module(Module)
+}
+
+/// A function of the program or a native one. It can be handed back to the
+/// program, but not called from Rust.
+#[derive(Clone)]
+pub struct Function(AliceObject);
+
+impl Function {
+    pub fn name(&self) -> &str {
+        match &self.0 {
+            AliceObject::Function(function) => function.name(),
+            AliceObject::Native(function) => function.name(),
+            _ => unreachable!("a Function holds a function")
+        }
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (AliceObject::Function(a), AliceObject::Function(b)) => Rc::ptr_eq(a, b),
+            (AliceObject::Native(a), AliceObject::Native(b)) => Rc::ptr_eq(a, b),
+            _ => false
+        }
+    }
+}
+
+impl Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&display::debug(&self.0))
+    }
+}
+
+/// A native module, as registered with `Engine::register_module`.
+#[derive(Clone)]
+pub struct Module(Rc<NativeModule>);
+
+impl Module {
+    #[inline]
+    pub fn name(&self) -> &str {
+        self.0.name()
+    }
+}
+
+impl PartialEq for Module {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Debug for Module {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl Display for Value {
+    /// Writes the value as `print` does.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&display::display(&self.clone().into()))
+    }
+}
+
+impl TryFrom<AliceObject> for Value {
+    type Error = AliceError;
+
+    fn try_from(value: AliceObject) -> Result<Self, Self::Error> {
+        convert(&value, &mut Vec::new())
+    }
+}
+
+/// Converts `value`, failing on an array that is among the `open` arrays
+/// it is inside of.
+fn convert(value: &AliceObject, open: &mut Vec<*const ()>) -> Result<Value, AliceError> {
+    Ok(match value {
+        AliceObject::Nil => Value::Nil,
+        AliceObject::Boolean(bool) => Value::Boolean(*bool),
+        AliceObject::I64(num) => Value::I64(*num),
+        AliceObject::F64(num) => Value::F64(*num),
+        AliceObject::String(str) => Value::String(str.to_string()),
+        AliceObject::Range(range) => Value::Range(range.start, range.end),
+        AliceObject::Function(_) | AliceObject::Native(_) => Value::Function(Function(value.clone())),
+        AliceObject::Module(module) => Value::Module(Module(module.clone())),
+        AliceObject::Array(array) => {
+            let ptr = Rc::as_ptr(array) as *const ();
+            if open.contains(&ptr) {
+                return Err(AliceError::native("Can't convert an array that contains itself."));
+            }
+
+            open.push(ptr);
+            let values: Result<Vec<_>, _> = array.borrow().iter().map(|value| convert(value, open)).collect();
+            open.pop();
+            Value::Array(values?)
+        }
+    })
+}
+
+/// The arguments of a call to a native function, still the program's own
+/// values. An argument is only copied into a `Value` when the function
+/// reads it, so arguments it doesn't read cost nothing, and an array that
+/// contains itself can be passed along as long as it isn't read.
+#[derive(Clone, Copy)]
+pub struct Args<'a>(pub(crate) &'a [AliceObject]);
+
+impl<'a> Args<'a> {
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Argument `index` converted to `T`. Panics if there is no such
+    /// argument, which the arity rules out for the ones it requires.
+    #[inline]
+    pub fn get<T: TryFrom<Value, Error = AliceError>>(&self, index: usize) -> Result<T, AliceError> {
+        Arg(&self.0[index]).get()
+    }
+
+    /// Argument `index` as a `Value`, panicking like `get`.
+    #[inline]
+    pub fn value(&self, index: usize) -> Result<Value, AliceError> {
+        Arg(&self.0[index]).to_value()
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = Arg<'a>> + 'a {
+        self.0.iter().map(Arg)
+    }
+}
+
+/// One of the `Args`.
+#[derive(Clone, Copy)]
+pub struct Arg<'a>(&'a AliceObject);
+
+impl Arg<'_> {
+    /// The name of the argument's type, as error messages give it, such as
+    /// `i64` or `array`.
+    #[inline]
+    pub fn type_name(&self) -> &'static str {
+        self.0.type_name()
+    }
+
+    /// The argument as a `Value`, copying arrays.
+    #[inline]
+    pub fn to_value(&self) -> Result<Value, AliceError> {
+        Value::try_from(self.0.clone())
+    }
+
+    /// The argument converted to `T`.
+    #[inline]
+    pub fn get<T: TryFrom<Value, Error = AliceError>>(&self) -> Result<T, AliceError> {
+        T::try_from(self.to_value()?)
+    }
+}
+
+/// The arrays made here aren't tracked by any heap; an engine taking the
+/// value in goes through `Heap::value` or `Heap::adopt`.
+impl From<Value> for AliceObject {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Nil => AliceObject::Nil,
+            Value::Boolean(bool) => AliceObject::Boolean(bool),
+            Value::I64(num) => AliceObject::I64(num),
+            Value::F64(num) => AliceObject::F64(num),
+            Value::String(str) => AliceObject::String(str.into()),
+            Value::Array(values) => AliceObject::array(values.into_iter().map(Into::into).collect()),
+            Value::Range(start, end) => AliceObject::Range(Rc::new(AliceRange { start, end })),
+            Value::Function(function) => function.0,
+            Value::Module(module) => AliceObject::Module(module.0)
+        }
+    }
+}
+
+impl From<i64> for Value {
+    #[inline]
+    fn from(num: i64) -> Self {
+        Value::I64(num)
+    }
+}
+
+impl From<i32> for Value {
+    #[inline]
+    fn from(num: i32) -> Self {
+        Value::I64(num.into())
+    }
+}
+
+impl From<f64> for Value {
+    #[inline]
+    fn from(num: f64) -> Self {
+        Value::F64(num)
+    }
+}
+
+impl From<bool> for Value {
+    #[inline]
+    fn from(bool: bool) -> Self {
+        Value::Boolean(bool)
+    }
+}
+
+impl From<()> for Value {
+    #[inline]
+    fn from(_: ()) -> Self {
+        Value::Nil
+    }
+}
+
+impl From<&str> for Value {
+    #[inline]
+    fn from(str: &str) -> Self {
+        Value::String(str.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    #[inline]
+    fn from(str: String) -> Self {
+        Value::String(str)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Nil, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(values: Vec<T>) -> Self {
+        Value::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<K: Into<String>, V: Into<Value>, S: BuildHasher> From<HashMap<K, V, S>> for Value {
+    /// Alice has no map type, so a map becomes an array of `[key, value]`
+    /// pairs sorted by key.
+    fn from(map: HashMap<K, V, S>) -> Self {
+        let mut pairs: Vec<(String, Value)> = map.into_iter().map(|(key, value)| (key.into(), value.into())).collect();
+        pairs.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        Value::Array(pairs.into_iter().map(|(key, value)| Value::Array(vec![Value::String(key), value])).collect())
+    }
+}
+
+impl TryFrom<Value> for i64 {
+    type Error = AliceError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::I64(num) => Ok(num),
+            value => Err(mismatch("an i64", &value))
+        }
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = AliceError;
+
+    /// Accepts an `i64` too, converting it.
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::F64(num) => Ok(num),
+            Value::I64(num) => Ok(num as f64),
+            value => Err(mismatch("a number", &value))
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = AliceError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Boolean(bool) => Ok(bool),
+            value => Err(mismatch("a bool", &value))
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = AliceError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(str) => Ok(str),
+            value => Err(mismatch("a string", &value))
+        }
+    }
+}
+
+impl<T: TryFrom<Value, Error = AliceError>> TryFrom<Value> for Vec<T> {
+    type Error = AliceError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Array(values) => values.into_iter().map(T::try_from).collect(),
+            value => Err(mismatch("an array", &value))
+        }
+    }
+}
+
+impl<T: TryFrom<Value, Error = AliceError>, S: BuildHasher + Default> TryFrom<Value> for HashMap<String, T, S> {
+    type Error = AliceError;
+
+    /// Expects an array of `[key, value]` pairs with string keys.
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        let Value::Array(pairs) = value else {
+            return Err(mismatch("an array of [key, value] pairs", &value));
+        };
+
+        pairs.into_iter().map(|pair| match pair {
+            Value::Array(pair) => match <[Value; 2]>::try_from(pair) {
+                Ok([Value::String(key), value]) => Ok((key, T::try_from(value)?)),
+                Ok(pair) => Err(mismatch("a [key, value] pair with a string key", &Value::Array(pair.into()))),
+                Err(pair) => Err(mismatch("a [key, value] pair", &Value::Array(pair)))
+            },
+            pair => Err(mismatch("a [key, value] pair", &pair))
+        }).collect()
+    }
+}
+
+fn mismatch(expected: &str, found: &Value) -> AliceError {
+    AliceError::native(format!("Expected {} but found {}.", expected, display::debug(&found.clone().into())))
+}
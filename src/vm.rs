@@ -0,0 +1,353 @@
+use std::{rc::Rc, cell::RefCell};
+
+use crate::{ast::{AliceObject, CallSite, Stmt}, chunk::{Chunk, Op}, compiler::Compiler, engine::{Core, Engine, sealed::Sealed}, environment::Environment, error::AliceError, function::{AliceFunction, Body}, heap::Heap, host::{Host, OsHost}, native::Arity, ops};
+
+/// Stack-based virtual machine running chunks produced by the `Compiler`.
+/// Output, input and the like go through the `Host` `H`, as for the
+/// `Interpreter`.
+pub struct Vm<H: Host = OsHost> {
+    host: Rc<H>,
+    stack: Vec<AliceObject>,
+    frames: Vec<Frame>,
+    core: Core,
+    environment: Scope,
+    heap: Heap
+}
+
+impl<H: Host> Sealed for Vm<H> {
+    #[inline]
+    fn core(&self) -> &Core {
+        &self.core
+    }
+
+    #[inline]
+    fn core_mut(&mut self) -> &mut Core {
+        &mut self.core
+    }
+
+    #[inline]
+    fn heap(&self) -> &Heap {
+        &self.heap
+    }
+
+    #[inline]
+    fn heap_mut(&mut self) -> &mut Heap {
+        &mut self.heap
+    }
+
+    fn run_statements(&mut self, statements: &[Stmt]) -> Result<AliceObject, AliceError> {
+        let chunk = Compiler::new().compile_line(statements)?;
+        self.run_chunk(chunk)
+    }
+}
+
+impl<H: Host> Engine for Vm<H> {}
+
+/// The environments of the captured locals in scope, innermost first.
+type Scope = Option<Rc<RefCell<Environment>>>;
+
+/// Caller state saved by `Op::Call` and restored by `Op::Return`.
+struct Frame {
+    chunk: Rc<Chunk>,
+    ip: usize,
+    base: usize,
+    environment: Scope
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vm {
+    #[inline]
+    pub fn new() -> Vm {
+        Vm::with_host(OsHost::default())
+    }
+}
+
+impl<H: Host> Vm<H> {
+    pub fn with_host(host: H) -> Vm<H> {
+        let host = Rc::new(host);
+        Vm { core: Core::new(host.clone()), host, stack: Vec::new(), frames: Vec::new(), environment: None, heap: Heap::default() }
+    }
+
+    #[inline]
+    pub fn host(&self) -> &H {
+        &self.host
+    }
+
+    fn run_chunk(&mut self, chunk: Chunk) -> Result<AliceObject, AliceError> {
+        let result = self.execute(Rc::new(chunk));
+
+        self.stack.clear();
+        self.frames.clear();
+        self.environment = None;
+
+        result
+    }
+
+    fn execute(&mut self, mut chunk: Rc<Chunk>) -> Result<AliceObject, AliceError> {
+        let mut ip = 0;
+        let mut base = 0;
+
+        loop {
+            let op = chunk.code[ip];
+            ip += 1;
+
+            match op {
+                Op::Constant(index) => {
+                    let value = self.heap.literal(&chunk.constants[index as usize]);
+                    self.stack.push(value);
+                }
+                Op::Nil => self.stack.push(AliceObject::Nil),
+                Op::True => self.stack.push(AliceObject::Boolean(true)),
+                Op::False => self.stack.push(AliceObject::Boolean(false)),
+                Op::Pop => {
+                    self.pop();
+                }
+
+                Op::GetLocal(slot) => {
+                    let value = self.stack[base + slot as usize].clone();
+                    self.stack.push(value);
+                }
+                Op::SetLocal(slot) => {
+                    let value = self.peek(0).clone();
+                    self.stack[base + slot as usize] = value;
+                }
+                Op::PopLocals(count) => {
+                    self.stack.truncate(self.stack.len() - count as usize);
+                }
+                Op::GetCaptured(depth, slot) => {
+                    let value = self.scope().borrow().get_at(depth as usize, slot as usize);
+                    self.stack.push(value);
+                }
+                Op::SetCaptured(depth, slot) => {
+                    let value = self.peek(0).clone();
+                    self.scope().borrow_mut().assign_at(depth as usize, slot as usize, value);
+                }
+                Op::DefineCaptured => {
+                    let value = self.pop();
+                    self.scope().borrow_mut().define(value);
+                }
+                Op::GetGlobal(name) => {
+                    let value = self.core.globals.get(&chunk.tokens[name as usize])?;
+                    self.stack.push(value);
+                }
+                Op::SetGlobal(name) => {
+                    let value = self.peek(0).clone();
+                    self.core.globals.assign(&chunk.tokens[name as usize], value)?;
+                }
+                Op::DefineGlobal(name) => {
+                    let value = self.pop();
+                    let name = chunk.tokens[name as usize].symbol();
+                    self.core.globals.define(name, value);
+                }
+                Op::PushScope => {
+                    let environment = Environment::from(self.environment.take());
+                    self.environment = Some(self.heap.environment(environment));
+                }
+                Op::PopScope => {
+                    let parent = self.scope().borrow().parent();
+                    self.environment = parent;
+                }
+
+                Op::Unary(operator) => {
+                    let value = self.pop();
+                    let value = ops::unary(&chunk.tokens[operator as usize], value)?;
+                    self.stack.push(value);
+                }
+                Op::Binary(operator) => {
+                    let right = self.pop();
+                    let left = self.pop();
+                    let value = ops::binary(left, &chunk.tokens[operator as usize], right)?;
+                    self.stack.push(value);
+                }
+                Op::Array(count) => {
+                    let values = self.stack.split_off(self.stack.len() - count as usize);
+                    let value = self.heap.array(values);
+                    self.stack.push(value);
+                }
+                Op::GetIndex(bracket) => {
+                    let index = self.pop();
+                    let object = self.pop();
+                    let value = ops::get_index(object, &chunk.tokens[bracket as usize], index)?;
+                    self.stack.push(value);
+                }
+                Op::GetMember(name) => {
+                    let object = self.pop();
+                    let value = ops::get_member(object, &chunk.tokens[name as usize])?;
+                    self.stack.push(value);
+                }
+                Op::SetIndex(bracket) => {
+                    let value = self.pop();
+                    let index = self.pop();
+                    let object = self.pop();
+                    ops::set_index(object, &chunk.tokens[bracket as usize], index, value.clone())?;
+                    self.stack.push(value);
+                }
+                Op::Range(dots) => {
+                    let end = self.pop();
+                    let start = self.pop();
+                    let value = ops::range(start, &chunk.tokens[dots as usize], end)?;
+                    self.stack.push(value);
+                }
+
+                Op::Jump(target) => ip = target as usize,
+                Op::JumpIfFalse(target) => {
+                    if !ops::is_truthy(&self.pop()) {
+                        ip = target as usize;
+                    }
+                }
+                Op::JumpIfFalseKeep(target) => {
+                    if !ops::is_truthy(self.peek(0)) {
+                        ip = target as usize;
+                    }
+                }
+                Op::JumpIfTrueKeep(target) => {
+                    if ops::is_truthy(self.peek(0)) {
+                        ip = target as usize;
+                    }
+                }
+                Op::ForPrepare(value) => {
+                    match self.peek(0) {
+                        AliceObject::Array(..) |
+                        AliceObject::Range(..) => self.stack.push(AliceObject::I64(0)),
+                        _ => {
+                            let msg = "Expect Array or Range(..) expression.";
+                            return Err(AliceError::runtime(msg, &chunk.tokens[value as usize]));
+                        }
+                    }
+                }
+                Op::ForNext(exit) => {
+                    let len = self.stack.len();
+                    let AliceObject::I64(index) = self.stack[len - 1] else { unreachable!() };
+                    let item = match &self.stack[len - 2] {
+                        AliceObject::Array(array) => array.borrow().get(index as usize).cloned(),
+                        AliceObject::Range(range) if range.start + index < range.end => Some(AliceObject::I64(range.start + index)),
+                        _ => None
+                    };
+
+                    match item {
+                        Some(item) => {
+                            self.stack[len - 1] = AliceObject::I64(index + 1);
+                            self.stack.push(item);
+                        }
+                        None => ip = exit as usize
+                    }
+                }
+
+                Op::Closure(index) => {
+                    let prototype = &chunk.functions[index as usize];
+                    let function = AliceFunction {
+                        name: prototype.name.clone(),
+                        params: prototype.params.clone(),
+                        body: Body::Bytecode(prototype.chunk.clone()),
+                        closure: self.environment.clone()
+                    };
+                    self.stack.push(AliceObject::Function(Rc::new(function)));
+                }
+                Op::Call(count, site) => {
+                    let callee = self.stack.len() - count as usize - 1;
+                    if self.call_native(callee, &chunk.calls[site as usize])? {
+                        continue;
+                    }
+                    let (body, environment) = self.enter(count, &chunk.calls[site as usize])?;
+                    if self.frames.len() == self.core.max_call_depth {
+                        return Err(AliceError::runtime("Stack overflow.", &chunk.calls[site as usize].paren));
+                    }
+
+                    self.frames.push(Frame {
+                        chunk: std::mem::replace(&mut chunk, body),
+                        ip,
+                        base,
+                        environment: std::mem::replace(&mut self.environment, environment)
+                    });
+                    ip = 0;
+                    base = callee;
+                }
+                Op::TailCall(count, site) => {
+                    // A native callee leaves its result for the `Return`
+                    // that follows.
+                    if self.call_native(self.stack.len() - count as usize - 1, &chunk.calls[site as usize])? {
+                        continue;
+                    }
+
+                    // Reuse the current frame: the callee and its arguments
+                    // take the place of the returning function at `base`.
+                    let (body, environment) = self.enter(count, &chunk.calls[site as usize])?;
+                    self.stack.drain(base..self.stack.len() - count as usize - 1);
+
+                    chunk = body;
+                    ip = 0;
+                    self.environment = environment;
+                }
+                Op::Return => {
+                    let value = self.pop();
+
+                    let Some(frame) = self.frames.pop() else {
+                        return Ok(value);
+                    };
+
+                    self.stack.truncate(base);
+                    self.stack.push(value);
+
+                    chunk = frame.chunk;
+                    ip = frame.ip;
+                    base = frame.base;
+                    self.environment = frame.environment;
+                }
+            }
+        }
+    }
+
+    /// If the callee at `callee` is native, calls it and replaces it and its
+    /// arguments with the result.
+    fn call_native(&mut self, callee: usize, site: &CallSite) -> Result<bool, AliceError> {
+        let AliceObject::Native(function) = &self.stack[callee] else {
+            return Ok(false);
+        };
+
+        let value = function.clone().call(&mut self.heap, site, &self.stack[callee + 1..])?;
+        self.stack.truncate(callee);
+        self.stack.push(value);
+        Ok(true)
+    }
+
+    /// Checks the callee and arguments on top of the stack, returning the
+    /// function's code and the environment it closes over.
+    fn enter(&mut self, count: u32, site: &CallSite) -> Result<(Rc<Chunk>, Scope), AliceError> {
+        let callee = self.stack.len() - count as usize - 1;
+        let paren = &site.paren;
+
+        let function = match &self.stack[callee] {
+            AliceObject::Function(function) => function.clone(),
+            _ => return Err(AliceError::runtime("Can only call functions.", paren))
+        };
+        let Body::Bytecode(body) = &function.body else {
+            return Err(AliceError::runtime("Can only call functions.", paren));
+        };
+        site.positional_only()?;
+        if count as usize != function.arity() {
+            return Err(AliceError::runtime(Arity::Exact(function.arity()).mismatch(count as usize), paren));
+        }
+
+        Ok((body.clone(), function.closure.clone()))
+    }
+
+    #[inline]
+    fn pop(&mut self) -> AliceObject {
+        self.stack.pop().expect("compiler emitted a pop on an empty stack")
+    }
+
+    #[inline]
+    fn peek(&self, distance: usize) -> &AliceObject {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+
+    #[inline]
+    fn scope(&self) -> &Rc<RefCell<Environment>> {
+        self.environment.as_ref().expect("compiler emitted a local access outside any scope")
+    }
+}
@@ -0,0 +1,128 @@
+use alice::{Engine, Interpreter, Value, Vm, host::MemoryHost, scanner::Scanner};
+
+/// What `println(arguments)` writes on both engines, which must agree.
+fn printed(arguments: &str) -> String {
+    let source = format!("println({arguments})");
+    let mut interpreter = Interpreter::with_host(MemoryHost::new());
+    interpreter.eval_str(&source).unwrap();
+    let mut vm = Vm::with_host(MemoryHost::new());
+    vm.eval_str(&source).unwrap();
+
+    assert_eq!(vm.host().stdout(), interpreter.host().stdout(), "{source}");
+    interpreter.host().stdout().trim_end_matches('\n').to_owned()
+}
+
+/// The message of the error `source` fails with on both engines.
+fn error(source: &str) -> String {
+    let message = Interpreter::new().eval_str(source).unwrap_err().message;
+    assert_eq!(Vm::new().eval_str(source).unwrap_err().message, message, "{source}");
+    message.into()
+}
+
+#[test]
+fn floats_never_look_like_integers() {
+    assert_eq!(printed("1.0, 2.5, 10.0 / 4.0, 0.0, -3.0"), "1.0 2.5 2.5 0.0 -3.0");
+    assert_eq!(printed("100000000000000000000.0, 0.0000001, 0.00001"), "1e20 1e-7 0.00001");
+    assert_eq!(printed("[1.0, 2], \"x\" + 2.0"), "[1.0, 2] x2.0");
+    assert_eq!(Value::F64(3.0).to_string(), "3.0");
+}
+
+#[test]
+fn strings_are_quoted_with_escapes_inside_arrays() {
+    assert_eq!(printed(r#""a\"b""#), "a\"b");
+    assert_eq!(printed(r#"["a\"b", "back\\slash", "tab\there", "line\nbreak", "\u{7}"]"#),
+        r#"["a\"b", "back\\slash", "tab\there", "line\nbreak", "\u{7}"]"#);
+    assert_eq!(printed(r#"["\u{1F600}", "", "{}"]"#), "[\"\u{1F600}\", \"\", \"{}\"]");
+}
+
+#[test]
+fn quoted_strings_read_back_as_the_same_value() {
+    let value = Value::Array(vec![
+        Value::String("quote \" backslash \\ newline \n nul \0 bell \u{7}".into()),
+        Value::Array(vec![Value::String("\r\t".into()), Value::F64(2.0)])
+    ]);
+    assert_eq!(alice::eval(&value.to_string()).unwrap(), value);
+}
+
+#[test]
+fn invalid_escapes_are_syntax_errors() {
+    for (source, message) in [
+        (r#""\q""#, "unknown escape '\\q'."),
+        (r#""\u{zz}""#, "invalid unicode escape."),
+        (r#""\u{}""#, "invalid unicode escape."),
+        (r#""\u{d800}""#, "'d800' is not a unicode character.")
+    ] {
+        assert_eq!(error(source), message, "{source}");
+    }
+}
+
+#[test]
+fn dbg_shows_the_source_of_each_argument() {
+    let source = "let x = 2;\ndbg(x * 3, \"s\", [1.0]);\nlet alias = dbg;\nalias(x);\ndbg();";
+    let mut interpreter = Interpreter::with_host(MemoryHost::new());
+    assert_eq!(interpreter.eval_str(source).unwrap(), Value::Nil);
+    assert_eq!(interpreter.host().stderr(), "[line 2] x * 3 = 6\n[line 2] \"s\" = \"s\"\n[line 2] [1.0] = [1.0]\n[line 4] 2\n[line 5]\n");
+
+    let mut vm = Vm::with_host(MemoryHost::new());
+    vm.set_optimize(true);
+    vm.eval_str(source).unwrap();
+    assert_eq!(vm.host().stderr(), interpreter.host().stderr());
+}
+
+#[test]
+fn errors_name_the_types_involved() {
+    for (source, message) in [
+        ("-\"a\"", "Operand must be a number, not string."),
+        ("1 < 2.0", "Operands must be numbers of the same type, not i64 and f64."),
+        ("nil * 2", "Operands must be numbers of the same type, not nil and i64."),
+        ("1 + \"a\"", "Operands must be numbers of the same type or start with a string, not i64 and string."),
+        ("true[0]", "Can only index arrays, not bool."),
+        ("let a = 1; a[0] = 2;", "Can only index arrays, not i64."),
+        ("[1][\"0\"]", "Index must be an i64, not string."),
+        ("env(1)", "The variable name must be a string, not i64."),
+        ("exit(1.0)", "The exit status must be an i64, not f64."),
+        ("exit(256)", "Exit status 256 is out of range (0 to 255)."),
+        ("exit(-1)", "Exit status -1 is out of range (0 to 255)."),
+        ("[0..\"9\"]", "Range bounds must be i64s, not i64 and string.")
+    ] {
+        assert_eq!(error(source), message, "{source}");
+    }
+}
+
+#[test]
+fn range_errors_point_at_the_dots() {
+    let source = "let n = 1.5;\nfor i in [0..n] {}";
+    for error in [Interpreter::new().eval_str(source).unwrap_err(), Vm::new().eval_str(source).unwrap_err()] {
+        assert_eq!((error.line, error.column), (2, 12));
+        assert_eq!(error.span.map(|span| &source[span.start..span.end]), Some(".."));
+    }
+}
+
+#[test]
+fn columns_count_characters_from_the_start_of_each_line() {
+    let source = "let s = \"ä\nbö\"; s\n  \"ü\" + 2";
+    let tokens = Scanner::new(source).scan_tokens().unwrap();
+    let positions: Vec<_> = tokens.iter().map(|token| (token.line, token.column)).collect();
+    assert_eq!(positions, [(1, 1), (1, 5), (1, 7), (1, 9), (2, 4), (2, 6), (3, 3), (3, 7), (3, 9), (3, 10)]);
+}
+
+#[test]
+fn deeply_nested_arrays_are_cut_off_and_freed() {
+    let source = "
+        let a = [];
+        for i in [0..200000] { a = [a]; }
+        println(a);
+        let text = format(\"{:?}\", a);
+        a = nil;
+        text
+    ";
+    let expected = format!("{}[...]{}", "[".repeat(100), "]".repeat(100));
+
+    let mut interpreter = Interpreter::with_host(MemoryHost::new());
+    assert_eq!(interpreter.eval_str(source).unwrap(), Value::String(expected.clone()));
+    assert_eq!(interpreter.host().stdout(), expected.clone() + "\n");
+
+    let mut vm = Vm::with_host(MemoryHost::new());
+    assert_eq!(vm.eval_str(source).unwrap(), Value::String(expected.clone()));
+    assert_eq!(vm.host().stdout(), expected + "\n");
+}
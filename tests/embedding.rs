@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use alice::{Engine, Interpreter, Program, Stmt, Value, Vm, error::ErrorKind, native::{Arity, NativeModule}, scanner::Scanner};
+
+#[test]
+fn eval_returns_the_last_expression() {
+    assert_eq!(alice::eval("let a = [1, 2.5, \"three\"]; a").unwrap(),
+        Value::Array(vec![Value::I64(1), Value::F64(2.5), Value::String("three".into())]));
+    assert_eq!(alice::eval("let a = 1;").unwrap(), Value::Nil);
+    assert_eq!(alice::eval("[2..5]").unwrap(), Value::Range(2, 5));
+}
+
+#[test]
+fn exit_is_reported_as_its_own_kind() {
+    let error = alice::eval("exit(3);").unwrap_err();
+    assert_eq!(error.kind, ErrorKind::Exit(3));
+}
+
+#[test]
+fn globals_persist_between_calls() {
+    fn check(engine: &mut impl Engine) {
+        engine.set_global("base", 40);
+        engine.eval_str("let answer = base + 2;").unwrap();
+        assert_eq!(engine.get_global("answer").unwrap(), Value::I64(42));
+        assert_eq!(engine.eval_str("answer").unwrap(), Value::I64(42));
+        assert!(engine.get_global("missing").is_err());
+    }
+
+    check(&mut Interpreter::new());
+    check(&mut Vm::new());
+}
+
+#[test]
+fn values_are_copied_out_of_the_program() {
+    let mut interpreter = Interpreter::new();
+    let mut value = interpreter.eval_str("let a = [1, [2]]; a").unwrap();
+    if let Value::Array(values) = &mut value {
+        values[0] = Value::I64(10);
+    }
+    assert_eq!(interpreter.eval_str("a[0]").unwrap(), Value::I64(1));
+}
+
+#[test]
+fn cyclic_arrays_cannot_be_converted() {
+    let error = alice::eval("let a = [1]; a[0] = a; a").unwrap_err();
+    assert_eq!(error.message.as_ref(), "Can't convert an array that contains itself.");
+}
+
+#[test]
+fn conversions_round_trip() {
+    let map = HashMap::from([("b", 2), ("a", 1)]);
+    let value = Value::from(map);
+    assert_eq!(value.to_string(), r#"[["a", 1], ["b", 2]]"#);
+    let back: HashMap<String, i64> = value.try_into().unwrap();
+    assert_eq!(back["a"], 1);
+
+    assert_eq!(f64::try_from(Value::I64(2)).unwrap(), 2.0);
+    assert_eq!(Vec::<String>::try_from(Value::from(vec!["x", "y"])).unwrap(), ["x", "y"]);
+    assert_eq!(i64::try_from(Value::from("1")).unwrap_err().message.as_ref(), "Expected an i64 but found \"1\".");
+}
+
+#[test]
+fn natives_and_modules_take_values() {
+    fn check(engine: &mut impl Engine) {
+        engine.register_native("sum", Arity::AtLeast(0), |arguments| {
+            let mut total = 0;
+            for argument in arguments.iter() {
+                total += argument.get::<i64>()?;
+            }
+            Ok(Value::I64(total))
+        });
+        engine.register_module(NativeModule::new("math")
+            .value("answer", 42)
+            .function("double", 1, |arguments| Ok(Value::I64(arguments.get::<i64>(0)? * 2))));
+
+        assert_eq!(engine.eval_str("sum(1, 2, math.double(math.answer))").unwrap(), Value::I64(87));
+
+        let error = engine.eval_str("sum(1, \"two\")").unwrap_err();
+        assert_eq!(error.message.as_ref(), "Expected an i64 but found \"two\".");
+        assert_eq!(error.column, 13);
+
+        let error = engine.eval_str("math.missing").unwrap_err();
+        assert_eq!(error.message.as_ref(), "Module 'math' has no member 'missing'.");
+        let error = engine.eval_str("math.answer.digits").unwrap_err();
+        assert_eq!(error.message.as_ref(), "Can only get members of modules, not i64.");
+    }
+
+    check(&mut Interpreter::new());
+    check(&mut Vm::new());
+}
+
+#[test]
+fn functions_are_handles() {
+    let mut interpreter = Interpreter::new();
+    let function = interpreter.eval_str("fn f() { return 1; } f").unwrap();
+    match &function {
+        Value::Function(function) => assert_eq!(function.name(), "f"),
+        other => panic!("expected a function, found {other:?}")
+    }
+
+    interpreter.set_global("g", function);
+    assert_eq!(interpreter.eval_str("g()").unwrap(), Value::I64(1));
+}
+
+#[test]
+fn natives_only_copy_the_arguments_they_read() {
+    fn check(engine: &mut impl Engine) {
+        engine.register_native("describe", Arity::AtLeast(0), |arguments| {
+            let types: Vec<&str> = arguments.iter().map(|argument| argument.type_name()).collect();
+            Ok(Value::from(format!("{} arguments: {}", arguments.len(), types.join(", "))))
+        });
+        engine.register_native("copy", 1, |arguments| arguments.value(0));
+
+        engine.eval_str("let a = [1, 2]; a[1] = a;").unwrap();
+        assert_eq!(engine.eval_str("describe(a, 1, nil)").unwrap(), Value::from("3 arguments: array, i64, nil"));
+        assert_eq!(engine.eval_str("copy([1, [2]])").unwrap(), Value::from(vec![Value::I64(1), Value::from(vec![2])]));
+
+        let error = engine.eval_str("copy(a)").unwrap_err();
+        assert_eq!(error.message.as_ref(), "Can't convert an array that contains itself.");
+    }
+
+    check(&mut Interpreter::new());
+    check(&mut Vm::new());
+}
+
+#[test]
+#[allow(deprecated)]
+fn the_old_parser_and_interpreter_paths_still_work() {
+    let tokens = Scanner::new("let a = 1; print(a + 1);").scan_tokens().unwrap();
+    let statements: Vec<Stmt> = alice::parser::Parser::new(tokens).parse().unwrap();
+    assert!(matches!(statements[0], Stmt::Var { .. }));
+    alice::interpreter::Interpreter::new().interpret(statements).unwrap();
+
+    let source = "let a = 1;\na";
+    let program = Program::parse(source, Scanner::new(source).scan_tokens().unwrap()).unwrap();
+    assert_eq!(program.statements().len(), 2);
+}
+
+#[test]
+fn doc_comments_are_listed_with_their_declarations() {
+    let source = "
+        /// The answer.
+        let answer = 42;
+        let undocumented = 1;
+        /// Adds one
+        /// to `n`.
+        fn succ(n) {
+            /// A local.
+            let one = 1;
+            return n + one;
+        }
+        if true { /// In a block.
+        fn nested() {} }
+    ";
+    let program = Program::parse(source, Scanner::new(source).scan_tokens().unwrap()).unwrap();
+    assert_eq!(program.docs(), [
+        ("answer", "The answer."),
+        ("succ", "Adds one\nto `n`."),
+        ("one", "A local."),
+        ("nested", "In a block.")
+    ]);
+}
@@ -0,0 +1,74 @@
+use alice::{Engine, Interpreter, Value, Vm};
+
+/// The result of `format(arguments)` on both engines, which must agree.
+fn format(arguments: &str) -> Result<String, String> {
+    let source = format!("format({arguments})");
+    let run = |engine: &mut dyn FnMut(&str) -> Result<Value, alice::Error>| match engine(&source) {
+        Ok(Value::String(text)) => Ok(text),
+        Ok(value) => panic!("format returned {value:?}"),
+        Err(error) => Err(error.message.into())
+    };
+
+    let mut interpreter = Interpreter::new();
+    let mut vm = Vm::new();
+    let result = run(&mut |source| interpreter.eval_str(source));
+    assert_eq!(run(&mut |source| vm.eval_str(source)), result, "{source}");
+    result
+}
+
+#[test]
+fn placeholders() {
+    assert_eq!(format(r#""{} and {}", 1, "two""#).unwrap(), "1 and two");
+    assert_eq!(format(r#""{1} {0} {1}", "a", "b""#).unwrap(), "b a b");
+    assert_eq!(format(r#""{0} {}", "a""#).unwrap(), "a a");
+    assert_eq!(format(r#""{{}} {{{}}}", 1"#).unwrap(), "{} {1}");
+    assert_eq!(format(r#""{greeting}, {who}!", greeting: "hello", who: "alice""#).unwrap(), "hello, alice!");
+    assert_eq!(format(r#""{} {name} {}", 1, 2, name: "n""#).unwrap(), "1 n 2");
+    assert_eq!(format(r#""{x} {x:>3}", x: 7"#).unwrap(), "7   7");
+}
+
+#[test]
+fn width_fill_and_alignment() {
+    assert_eq!(format(r#""[{:<6}] [{:^7}] [{:>6}]", "ab", "mid", 42"#).unwrap(), "[ab    ] [  mid  ] [    42]");
+    assert_eq!(format(r#""[{:6}] [{:6}]", "ab", 42"#).unwrap(), "[ab    ] [    42]");
+    assert_eq!(format(r#""[{:*^9}] [{:_<4}]", "hi", 1"#).unwrap(), "[***hi****] [1___]");
+    assert_eq!(format(r#""[{:08.3}] [{:05}] [{:+05}]", -2.5, -42, 7"#).unwrap(), "[-002.500] [-0042] [+0007]");
+    assert_eq!(format(r#""[{:2}]", "longer""#).unwrap(), "[longer]");
+}
+
+#[test]
+fn numbers() {
+    assert_eq!(format(r#""{:+} {:+} {:+.1}", 7, -7, 2.25"#).unwrap(), "+7 -7 +2.2");
+    assert_eq!(format(r#""{:.2} {:.0}", 3.14159, 2.5"#).unwrap(), "3.14 2");
+    assert_eq!(format(r#""{:x} {:X} {:#x} {:b} {:#010b} {:o} {:#o}", 255, 255, 255, 5, 5, 8, 8"#).unwrap(), "ff FF 0xff 101 0b00000101 10 0o10");
+    assert_eq!(format(r#""{:e} {:.2E} {:e}", 1234.5, 0.000123, 10"#).unwrap(), "1.2345e3 1.23E-4 1e1");
+}
+
+#[test]
+fn debug_and_precision_of_other_values() {
+    assert_eq!(format(r#""{:?} {:?} {:?}", "quote", 12, nil"#).unwrap(), "\"quote\" 12 nil");
+    assert_eq!(format(r#""{} {:?}", [1, "a"], [1, "a"]"#).unwrap(), "[1, \"a\"] [1, \"a\"]");
+    assert_eq!(format(r#""{:.3}|{:>6.2}|", "truncate", "abc""#).unwrap(), "tru|    ab|");
+}
+
+#[test]
+fn errors() {
+    for (arguments, message) in [
+        (r#""{} {}", 1"#, "Format string refers to argument 1 but only 1 were given."),
+        (r#""{3}", 1"#, "Format string refers to argument 3 but only 1 were given."),
+        (r#""{name}", 1"#, "No argument named 'name'."),
+        (r#""{", 1"#, "Invalid format string: unmatched '{' at 0."),
+        (r#""a }", 1"#, "Invalid format string: unmatched '}' at 2."),
+        (r#""{:y}", 1"#, "Invalid format spec 'y'."),
+        (r#""{:.}", 1"#, "Invalid format spec '.'."),
+        (r#""{}", 1, 2"#, "Argument 1 is never used by the format string."),
+        (r#""{a}", a: 1, b: 2"#, "Keyword argument 'b' is never used by the format string.")
+    ] {
+        assert_eq!(format(arguments).unwrap_err(), message, "{arguments}");
+    }
+
+    assert_eq!(format(r#""{:x}", 1.5"#).unwrap_err(), "'x' formats i64 values, not f64.");
+    assert_eq!(format(r#""{:e}", "s""#).unwrap_err(), "'e' formats numbers, not string.");
+    assert_eq!(format("1").unwrap_err(), "The format string must be a string, not i64.");
+    assert_eq!(format("").unwrap_err(), "Expected at least 1 argument but got 0.");
+}
@@ -0,0 +1,155 @@
+use alice::{Engine, Interpreter, Value, Vm, host::MemoryHost, native::NativeModule};
+
+/// Objects an engine may track at once before a collection must have run.
+const BOUND: usize = 3 * 4096;
+
+#[test]
+fn cycles_are_reclaimed() {
+    fn check(engine: &mut impl Engine) {
+        engine.eval_str("let a = [0]; a[0] = a; fn f() { return f; }").unwrap();
+        engine.eval_str("a = nil; f = nil;").unwrap();
+        assert!(engine.collect_garbage() >= 1);
+        assert_eq!(engine.heap_stats().total(), 0);
+    }
+
+    check(&mut Interpreter::new());
+    check(&mut Vm::new());
+}
+
+#[test]
+fn arrays_made_outside_the_program_are_reclaimed() {
+    // Each cycle holds on to an array the program made, which the heap
+    // tracks and counts however the cycle's own arrays were made.
+    fn check(engine: &mut impl Engine) {
+        engine.set_args(vec!["x".into(), "y".into()]);
+        engine.set_global("g", Value::Array(vec![Value::Array(vec![Value::Nil, Value::Nil])]));
+        engine.register_native("pair", 0, |_| Ok(Value::Array(vec![Value::Nil, Value::Nil])));
+        engine.register_module(NativeModule::new("m").value("a", Value::Array(vec![Value::Nil, Value::Nil])));
+
+        engine.eval_str("
+            for i in [0..10000] {
+              let v = env_vars();
+              v[0][0] = v;
+              v[0][1] = [i];
+              let a = args();
+              a[0] = a;
+              a[1] = [i];
+              let d = dbg(1, 2);
+              d[0] = d;
+              d[1] = [i];
+              let p = pair();
+              p[0] = p;
+              p[1] = [i];
+            }
+            g[0][0] = g;
+            g[0][1] = [0];
+            m.a[0] = m.a;
+            m.a[1] = [0];
+        ").unwrap();
+        let peak = engine.heap_stats().peak;
+        assert!(peak < BOUND, "peak of {} tracked objects", peak);
+
+        engine.eval_str("g = nil; m = nil;").unwrap();
+        engine.collect_garbage();
+        assert_eq!(engine.heap_stats().total(), 0);
+    }
+
+    check(&mut Interpreter::with_host(MemoryHost::new().with_env("HOME", "/home/alice")));
+    check(&mut Vm::with_host(MemoryHost::new().with_env("HOME", "/home/alice")));
+}
+
+#[test]
+fn cycle_heavy_loops_run_in_bounded_memory() {
+    fn check(engine: &mut impl Engine) {
+        let total = engine.eval_str("
+            let total = 0;
+            for i in [0..100000] {
+              let a = [i];
+              a[0] = a;
+              fn f() { return a; }
+              total = total + i;
+            }
+            total
+        ").unwrap();
+        assert_eq!(total, Value::I64(4999950000));
+
+        let peak = engine.heap_stats().peak;
+        assert!(peak < BOUND, "peak of {} tracked objects", peak);
+    }
+
+    check(&mut Interpreter::new());
+    check(&mut Vm::new());
+}
+
+#[test]
+fn long_sessions_run_in_bounded_memory() {
+    fn check(engine: &mut impl Engine) {
+        for i in 0..10000 {
+            let source = format!("let c{0} = [{0}]; c{0}[0] = c{0}; fn f{0}() {{ return c{0}; }} c{0} = nil; {0} + 1", i % 50);
+            assert_eq!(engine.eval_str(&source).unwrap(), Value::I64(i % 50 + 1));
+        }
+
+        let peak = engine.heap_stats().peak;
+        assert!(peak < BOUND, "peak of {} tracked objects", peak);
+    }
+
+    check(&mut Interpreter::new());
+    check(&mut Vm::new());
+}
+
+#[test]
+fn values_held_by_the_embedder_survive() {
+    fn check(engine: &mut impl Engine) {
+        let get = engine.eval_str("
+            fn make() {
+              let a = [1, 2];
+              a[1] = a;
+              fn get() { return a[0]; }
+              return get;
+            }
+            let get = make();
+            get
+        ").unwrap();
+
+        // The closure is only held by `get` now.
+        engine.eval_str("get = nil;").unwrap();
+        engine.collect_garbage();
+
+        engine.set_global("again", get);
+        assert_eq!(engine.eval_str("again()").unwrap(), Value::I64(1));
+    }
+
+    check(&mut Interpreter::new());
+    check(&mut Vm::new());
+}
+
+#[test]
+fn values_held_by_natives_survive() {
+    fn check(engine: &mut (impl Engine + 'static)) {
+        let held = std::rc::Rc::new(std::cell::RefCell::new(Value::Nil));
+        let store = held.clone();
+        engine.register_native("hold", 1, move |arguments| {
+            *store.borrow_mut() = arguments.value(0)?;
+            Ok(Value::Nil)
+        });
+        let load = held.clone();
+        engine.register_native("held", 0, move |_| Ok(load.borrow().clone()));
+
+        engine.eval_str("
+            fn make(n) {
+              fn count() { n = n + 1; return n; }
+              return count;
+            }
+            hold(make(10));
+        ").unwrap();
+
+        // Churn enough to collect several times while only the native holds
+        // the closure.
+        engine.eval_str("for i in [0..20000] { let a = [i]; a[0] = a; }").unwrap();
+        engine.collect_garbage();
+        assert_eq!(engine.eval_str("let count = held(); count(); count()").unwrap(), Value::I64(12));
+    }
+
+    check(&mut Interpreter::new());
+    check(&mut Vm::new());
+}
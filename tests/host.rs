@@ -0,0 +1,142 @@
+use std::time::Duration;
+
+use alice::{Engine, Interpreter, Value, Vm, host::{Host, MemoryHost}};
+
+const SCRIPT: &str = r#"
+print("a", 1);
+println("", [2, "b"]);
+eprintln("oops");
+println(env("HOME"), env("MISSING"));
+env_vars()
+"#;
+
+fn host() -> MemoryHost {
+    MemoryHost::new()
+        .with_env("HOME", "/home/alice")
+        .with_env("LANG", "C")
+}
+
+#[test]
+fn output_and_environment_go_through_the_host() {
+    let mut interpreter = Interpreter::with_host(host());
+    let vars = interpreter.eval_str(SCRIPT).unwrap();
+    assert_eq!(interpreter.host().stdout(), "a 1 [2, \"b\"]\n/home/alice nil\n");
+    assert_eq!(interpreter.host().stderr(), "oops\n");
+    assert_eq!(vars.to_string(), r#"[["HOME", "/home/alice"], ["LANG", "C"]]"#);
+
+    let mut vm = Vm::with_host(host());
+    assert_eq!(vm.eval_str(SCRIPT).unwrap(), vars);
+    assert_eq!(vm.host().stdout(), interpreter.host().stdout());
+    assert_eq!(vm.host().stderr(), interpreter.host().stderr());
+}
+
+#[test]
+fn print_formats_a_leading_string_when_given_more() {
+    let script = r#"
+        print("{} and {}");
+        println(" {:>3} and {:.1}", 1, 2.25, "rest");
+        println(" |", "{{");
+        println(1, [2, 3], "x", sep: ", ");
+        eprint("a{}", "b", "c", sep: "");
+    "#;
+
+    let mut interpreter = Interpreter::with_host(MemoryHost::new());
+    interpreter.eval_str(script).unwrap();
+    assert_eq!(interpreter.host().stdout(), "{} and {}   1 and 2.2 rest\n | {{\n1, [2, 3], x\n");
+    assert_eq!(interpreter.host().stderr(), "abc");
+
+    let mut vm = Vm::with_host(MemoryHost::new());
+    vm.eval_str(script).unwrap();
+    assert_eq!(vm.host().stdout(), interpreter.host().stdout());
+    assert_eq!(vm.host().stderr(), interpreter.host().stderr());
+}
+
+#[test]
+fn keyword_arguments_are_checked() {
+    for (script, message) in [
+        (r#"println(1, sep: 2)"#, "The separator must be a string, not i64."),
+        (r#"println(1, end: "")"#, "Unexpected keyword argument 'end'."),
+        (r#"fn f(x) { return x; } f(x: 1)"#, "Unexpected keyword argument 'x'."),
+        (r#"fn f(x) { return print(x, sep: 1); } f(1)"#, "The separator must be a string, not i64.")
+    ] {
+        let error = Interpreter::with_host(MemoryHost::new()).eval_str(script).unwrap_err();
+        assert_eq!(error.message.as_ref(), message, "{script}");
+        let error = Vm::with_host(MemoryHost::new()).eval_str(script).unwrap_err();
+        assert_eq!(error.message.as_ref(), message, "{script}");
+    }
+
+    for (script, message) in [
+        (r#"println(sep: "", 1)"#, "Expect keyword arguments after positional ones."),
+        (r#"println(1, sep: "", sep: "")"#, "Keyword argument 'sep' is given twice.")
+    ] {
+        let error = Interpreter::with_host(MemoryHost::new()).eval_str(script).unwrap_err();
+        assert_eq!(error.message.as_ref(), message, "{script}");
+    }
+}
+
+#[test]
+fn dbg_writes_to_stderr() {
+    let mut interpreter = Interpreter::with_host(MemoryHost::new());
+    assert_eq!(interpreter.eval_str("dbg(1 + 1)").unwrap(), Value::I64(2));
+    assert_eq!(interpreter.host().stdout(), "");
+    assert!(interpreter.host().stderr().starts_with("[line 1] "));
+}
+
+#[test]
+fn input_files_clock_and_randomness_go_through_the_host() {
+    let script = r#"
+        let name = read_line();
+        write_file("out.txt", format("hello, {}", name));
+        let r = random();
+        [read_file("in.txt"), read_line(), clock(), r >= 0.0 and r < 1.0]
+    "#;
+    let host = || {
+        let host = MemoryHost::new().with_stdin("alice\n").with_file("in.txt", "contents").with_seed(3);
+        host.set_time(Duration::from_millis(1500));
+        host
+    };
+
+    let mut interpreter = Interpreter::with_host(host());
+    let value = interpreter.eval_str(script).unwrap();
+    assert_eq!(value.to_string(), r#"["contents", nil, 1.5, true]"#);
+    assert_eq!(interpreter.host().file("out.txt").as_deref(), Some("hello, alice"));
+
+    let mut vm = Vm::with_host(host());
+    assert_eq!(vm.eval_str(script).unwrap(), value);
+    assert_eq!(vm.host().file("out.txt"), interpreter.host().file("out.txt"));
+
+    let error = Interpreter::with_host(MemoryHost::new()).eval_str("read_file(\"missing.txt\")").unwrap_err();
+    assert_eq!(error.message.as_ref(), "Can't read 'missing.txt': No such file.");
+    let error = Vm::with_host(MemoryHost::new()).eval_str("write_file(\"out.txt\", 1)").unwrap_err();
+    assert_eq!(error.message.as_ref(), "The contents must be a string, not i64.");
+}
+
+#[test]
+fn memory_host_reads_what_it_was_given() {
+    let host = MemoryHost::new()
+        .with_stdin("first\r\nsecond")
+        .with_file("in.txt", "contents");
+
+    assert_eq!(host.read_line().unwrap().as_deref(), Some("first"));
+    assert_eq!(host.read_line().unwrap().as_deref(), Some("second"));
+    assert_eq!(host.read_line().unwrap(), None);
+
+    assert_eq!(host.read_file("in.txt").unwrap(), "contents");
+    assert!(host.read_file("missing.txt").is_err());
+    host.write_file("out.txt", "written").unwrap();
+    assert_eq!(host.file("out.txt").as_deref(), Some("written"));
+
+    assert_eq!(host.env_var("HOME"), None);
+    assert!(host.env_vars().is_empty());
+}
+
+#[test]
+fn memory_host_is_deterministic() {
+    let a = MemoryHost::new().with_seed(7);
+    let b = MemoryHost::new().with_seed(7);
+    assert_eq!([a.random(), a.random()], [b.random(), b.random()]);
+    assert_ne!(a.random(), a.random());
+
+    a.set_time(Duration::from_secs(5));
+    assert_eq!(a.now(), Duration::from_secs(5));
+}
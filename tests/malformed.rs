@@ -0,0 +1,101 @@
+use alice::{Engine, Interpreter, Vm, host::MemoryHost};
+
+/// Runs `source` on both engines, with and without the optimizer, checking
+/// that they agree on its value or the message it fails with.
+fn run(source: &str) -> Result<String, String> {
+    fn check(engine: &mut impl Engine, optimize: bool, source: &str) -> Result<String, String> {
+        engine.set_optimize(optimize);
+        engine.eval_str(source).map(|value| value.to_string()).map_err(|e| e.message.into())
+    }
+
+    let mut results = Vec::new();
+    for optimize in [false, true] {
+        results.push(check(&mut Interpreter::with_host(MemoryHost::new()), optimize, source));
+        results.push(check(&mut Vm::with_host(MemoryHost::new()), optimize, source));
+    }
+
+    let result = results.pop().expect("every run has a result");
+    for other in results {
+        assert_eq!(other, result, "{source}");
+    }
+    result
+}
+
+#[test]
+fn long_operator_chains_are_not_nesting() {
+    let chain = format!("1{}", " + 1".repeat(5000));
+    assert_eq!(run(&chain), Ok("5001".into()));
+    assert_eq!(run(&format!("({chain}) * 2 - 2")), Ok("10000".into()));
+    assert_eq!(run(&format!("true{} or false", " and 1 < 2".repeat(3000))), Ok("true".into()));
+    assert_eq!(run(&format!("let a = {chain};\nlet b = {chain};\na + b")), Ok("10002".into()));
+
+    let error = run(&format!("1{}", " + 1".repeat(10_001))).unwrap_err();
+    assert_eq!(error, "Statement has too many operators (more than 10000).");
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    // Code nested right up to the limit runs on a test thread's default
+    // stack.
+    assert_eq!(run(&format!("{}1{}", "(".repeat(48), ")".repeat(48))), Ok("1".into()));
+    assert_eq!(run(&format!("{}1{}", "[".repeat(48), "]".repeat(48))), Ok("[".repeat(48) + "1" + &"]".repeat(48)));
+    assert_eq!(run(&format!("{}1;{}", "{".repeat(48), "}".repeat(48))), Ok("nil".into()));
+    assert_eq!(run(&format!("{}1", "-".repeat(48))), Ok("1".into()));
+    assert_eq!(run(&format!("fn f(x) {{ return x; }} {}1{}", "f(".repeat(24), ")".repeat(24))), Ok("1".into()));
+    assert_eq!(run(&format!("let t = true;\n{}1", "if t ".repeat(48))), Ok("nil".into()));
+
+    for source in [
+        format!("{}1{}", "(".repeat(49), ")".repeat(49)),
+        format!("{}1{}", "[".repeat(1000), "]".repeat(1000)),
+        format!("{}1;{}", "{".repeat(1000), "}".repeat(1000)),
+        format!("{}1", "-".repeat(1000)),
+        format!("{}1{}", "f(".repeat(1000), ")".repeat(1000)),
+        format!("{}1", "if true ".repeat(1000)),
+        format!("a{}", "[0]".repeat(1000)),
+        "let a = 1".to_owned() + &" = 1".repeat(1000)
+    ] {
+        assert_eq!(run(&source).unwrap_err(), "Code is nested too deeply (more than 50 levels).", "{}", &source[..20]);
+    }
+}
+
+#[test]
+fn line_breaks_end_statements_unless_continued() {
+    assert_eq!(run("let c = \"c\"\nlet s = \"a\nb\" + c\ns"), Ok("a\nbc".into()));
+    assert_eq!(run("let a = 1\nlet b = a\n  + 2;\nb"), Ok("3".into()));
+    assert_eq!(run("let a = 1\nlet b = a\n  + 2\n  * (3\n  - 1);\nb"), Ok("5".into()));
+    assert_eq!(run("let a = 1\nlet b = a +\n  2\nb"), Ok("3".into()));
+    assert_eq!(run("let a = 1\n-2\na"), Ok("1".into()));
+    assert_eq!(run("let a = 1\nlet b = a\n  + 2\nb").unwrap_err(), "Expect expression.");
+}
+
+#[test]
+fn malformed_input_is_an_error() {
+    for source in [
+        "(", ")", "]", "}", "..", "[1..", "[0..]", "[1, 2", "1 +", "-", "a =", "1 = 2", "fn", "fn f(", "fn f() {", "let",
+        "let x =", "let a = 1 let b", "if", "for x in", "for 1 in [0..1] {}", "return", "\"abc", "\"\\q\"", "\"\\u{110000}\"",
+        "99999999999999999999", "1.e", "/* x", "a.", "@#$", "f(a: 1, 2)", "print(sep: 1, sep: 2)", "\0", "é"
+    ] {
+        assert!(run(source).is_err(), "{source}");
+    }
+}
+
+#[test]
+fn every_prefix_of_a_program_is_handled() {
+    let program = r#"
+        /// Sums a range of odd numbers.
+        fn sums(n) {
+            let total = 0;
+            for i in [0..n] { total = total + i * 2 - 1; }
+            return [total, -total, !true or n >= 3 and "s" + n];
+        }
+        let r = sums(4); // [8, -8, "s4"]
+        println(format("{:>4} {x}", r[0], x: r[2]), sep: "\t");
+        dbg(r[1] % 3, 1.5 / 0.5);
+        r
+    "#;
+
+    for end in (0..=program.len()).filter(|&end| program.is_char_boundary(end)) {
+        let _ = run(&program[..end]);
+    }
+    assert_eq!(run(program), Ok("[8, -8, \"s4\"]".into()));
+}
@@ -0,0 +1,145 @@
+use alice::{Engine, Interpreter, Value, Vm, host::MemoryHost};
+
+/// Runs `source` on both engines, with and without the optimizer, and
+/// checks that they all write the same output and end the same way.
+fn run(source: &str) -> String {
+    fn check(engine: &mut impl Engine, optimize: bool, source: &str) -> Result<String, String> {
+        engine.set_optimize(optimize);
+        engine.eval_str(source).map(|value| value.to_string()).map_err(|e| format!("line {}: {}", e.line, e.message))
+    }
+
+    let mut results = Vec::new();
+    for optimize in [false, true] {
+        let mut interpreter = Interpreter::with_host(MemoryHost::new());
+        let result = check(&mut interpreter, optimize, source);
+        results.push((result, interpreter.host().stdout(), interpreter.host().stderr()));
+
+        let mut vm = Vm::with_host(MemoryHost::new());
+        let result = check(&mut vm, optimize, source);
+        results.push((result, vm.host().stdout(), vm.host().stderr()));
+    }
+
+    let (result, stdout, stderr) = results.pop().expect("every run has a result");
+    for other in results {
+        assert_eq!(other, (result.clone(), stdout.clone(), stderr.clone()), "{source}");
+    }
+    match result {
+        Ok(value) => stdout + &value,
+        Err(e) => stdout + &e
+    }
+}
+
+#[test]
+fn closures_share_captured_locals() {
+    let output = run("
+        fn counter(start) {
+            let n = start;
+            fn step(by) { n = n + by; return n; }
+            fn get() { return n; }
+            return [step, get];
+        }
+        let c = counter(10);
+        c[0](1);
+        c[0](2);
+        [c[1](), counter(0)[1]()]
+    ");
+    assert_eq!(output, "[13, 0]");
+}
+
+#[test]
+fn loop_variables_are_fresh_on_every_iteration() {
+    let output = run("
+        let fs = [nil, nil, nil];
+        for i in [0..3] {
+            let j = i * 10;
+            fn f() { return i + j; }
+            fs[i] = f;
+        }
+        [fs[0](), fs[1](), fs[2]()]
+    ");
+    assert_eq!(output, "[0, 11, 22]");
+}
+
+#[test]
+fn captured_and_plain_locals_mix_in_one_scope() {
+    let output = run("
+        fn f(a, b, c) {
+            let x = a + 1;
+            let y = b + 1;
+            {
+                let z = c + 1;
+                let w = 0;
+                fn g() { return [b, y, z]; }
+                w = x + z;
+                y = 100;
+                return [g(), w, a, x];
+            }
+        }
+        f(1, 2, 3)
+    ");
+    assert_eq!(output, "[[2, 100, 4], 6, 1, 2]");
+}
+
+#[test]
+fn local_functions_recurse_and_shadow() {
+    let output = run("
+        let total = 0;
+        {
+            fn fact(n) { if n <= 1 { return 1; } return n * fact(n - 1); }
+            let x = 1;
+            {
+                let x = 2;
+                for x in [3..5] { total = total + x; }
+                total = total + x;
+            }
+            total = total + x + fact(5);
+        }
+        total
+    ");
+    assert_eq!(output, "130");
+}
+
+#[test]
+fn tail_calls_keep_their_locals() {
+    let output = run("
+        fn count(n, acc) {
+            let next = acc + 1;
+            fn peek() { return next; }
+            if n == 0 { return peek(); }
+            return count(n - 1, next);
+        }
+        count(100000, 0)
+    ");
+    assert_eq!(output, "100001");
+}
+
+#[test]
+fn output_and_errors_agree() {
+    assert_eq!(run(r#"println("a", [1.5, "b"], sep: "|"); dbg(1 + 1); 3"#), "a|[1.5, \"b\"]\n3");
+    assert_eq!(run("fn f(x) { let y = x; return y / 0; }\nf(1)"), "line 1: Division by zero.");
+    assert_eq!(run("for i in [0..3] {\n  let a = [i];\n  a[i];\n}"), "line 3: Index 1 out of bounds for array of length 1.");
+    assert_eq!(run("fn f(a) { return a; }\nf(1, 2)"), "line 2: Expected 1 argument but got 2.");
+}
+
+#[test]
+fn deep_recursion_fails_instead_of_overflowing() {
+    let deep = "fn f(n) { if n == 0 { return 0; } return 1 + f(n - 1); }\n";
+    assert_eq!(run(&format!("{deep}f(99)")), "99");
+    assert_eq!(run(&format!("{deep}f(100)")), "line 1: Stack overflow.");
+    assert_eq!(run("fn f(n) {\n  return 1 + f(n - 1);\n}\nf(200000)"), "line 2: Stack overflow.");
+
+    // The engines stay usable, at full depth, after the error.
+    fn reuse(engine: &mut impl Engine, deep: &str, depth: i64) {
+        engine.eval_str(deep).unwrap();
+        assert!(engine.eval_str("f(5000)").is_err());
+        assert_eq!(engine.eval_str(&format!("f({})", depth - 1)).unwrap(), Value::I64(depth - 1));
+    }
+    reuse(&mut Interpreter::new(), deep, 100);
+    reuse(&mut Vm::new(), deep, 100);
+
+    // The VM keeps calls off the native stack, so it goes deeper without
+    // a bigger thread.
+    let mut vm = Vm::new();
+    vm.set_max_call_depth(1000);
+    reuse(&mut vm, deep, 1000);
+}